@@ -1,14 +1,21 @@
 use std::env;
-use std::fs::File;
-use std::io::Write;
-use std::path::PathBuf;
+
+#[path = "src/manifest.rs"]
+#[allow(dead_code)]
+mod manifest;
+
 fn main() {
-    let out = &PathBuf::from(env::var_os("OUT_DIR").unwrap());
-    File::create(out.join("userosc.x"))
-        .unwrap()
-        .write_all(include_bytes!("scripts/userosc.x"))
-        .unwrap();
-    println!("cargo:rustc-link-search={}", out.display());
-    println!("cargo:rerun-if-changed=scripts/userosc.x");
+    // The linker script itself is provided by the `logue` crate.
+    if env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("none") {
+        println!("cargo:rustc-link-arg-bins=--script=userosc.x");
+    }
+
+    // Reject a manifest the firmware would not load. The manifest.json
+    // itself is written for the platform when the unit is packaged.
+    if let Err(e) = manifest::MANIFEST.validate() {
+        panic!("invalid manifest in src/manifest.rs: {}", e);
+    }
+
     println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/manifest.rs");
 }
@@ -36,6 +36,12 @@ impl Coeffs {
     }
 }
 
+impl Default for Coeffs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub struct BiQuad {
     pub coeffs: Coeffs,
     z1: f32,
@@ -51,11 +57,22 @@ impl BiQuad {
         }
     }
 
+    /// The delay element, for tests to check on.
+    #[cfg(test)]
+    pub(crate) fn z1(&self) -> f32 {
+        self.z1
+    }
+
     pub fn process_fo(&mut self, xn: f32) -> f32 {
         let acc = self.coeffs.ff0 * xn + self.z1;
         self.z1 = self.coeffs.ff1 * xn;
         self.z1 -= self.coeffs.fb1 * acc;
-        return acc;
+        acc
+    }
+}
 
+impl Default for BiQuad {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -1,22 +1,23 @@
 #![no_std]
+// Constants are transcribed digit-for-digit from `waves.hpp`.
+#![allow(clippy::excessive_precision)]
 
-use panic_halt as _;
 use core::f32;
 use core::ptr;
 use micromath::F32Ext;
 
 pub mod dsp;
-pub mod mathutil;
-pub mod nts1;
+pub mod manifest;
 
 use dsp::biquad;
-use mathutil::*;
-use nts1::*;
-use nts1::clipsat::osc_softclipf;
-use nts1::platform::*;
-use nts1::random::osc_white;
-use nts1::userosc::*;
-use nts1::wavebank::*;
+use logue::*;
+use logue::clipsat::osc_softclipf;
+use logue::mathutil::*;
+use logue::platform::*;
+use logue::random::osc_white;
+use logue::userosc::*;
+use logue::wavebank::*;
+use manifest::*;
 
 #[repr(u8)]
 pub enum RavesFlags {
@@ -87,6 +88,13 @@ impl RavesState {
 
 }
 
+impl Default for RavesState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug)]
 #[repr(C)]
 pub struct RavesParams {
     submix: f32,
@@ -116,6 +124,12 @@ impl RavesParams {
     }
 }
 
+impl Default for RavesParams {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[repr(C)]
 pub struct Raves {
     state: RavesState,
@@ -199,6 +213,12 @@ impl Raves {
     }
 }
 
+impl Default for Raves {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub fn osc_init(raves: &mut Raves, _platform: u32, _api: u32) {
     raves.init();
 }
@@ -217,11 +237,11 @@ pub fn osc_cycle(raves: &mut Raves, params: &UserOscParams, yn: &mut [i32]) {
     {
         let sm : &mut RavesState = &mut raves.state;
 
-        if (flags as u8) & (RavesFlags::Reset as u8) != 0 {
+        if flags & (RavesFlags::Reset as u8) != 0 {
             sm.reset();
         }
 
-        if (flags as u8) & (RavesFlags::BitCrush as u8) != 0 {
+        if flags & (RavesFlags::BitCrush as u8) != 0 {
             sm.dither = p.bitcrush * 2e-008;
             sm.bitres = osc_bitresf(p.bitcrush);
             sm.bitresrcp = 1.0 / sm.bitres;
@@ -248,16 +268,21 @@ pub fn osc_cycle(raves: &mut Raves, params: &UserOscParams, yn: &mut [i32]) {
     let submix = p.submix;
     let ringmix = p.ringmix;
 
+    // The wave pointers are set in `init`, before the first cycle.
+    let wave0 = unsafe { wave_table_ref(s.wave0) };
+    let wave1 = unsafe { wave_table_ref(s.wave1) };
+    let subwave = unsafe { wave_table_ref(s.subwave) };
+
     let prelpf = &mut raves.prelpf;
     let postlpf = &mut raves.postlpf;
 
     for y in yn.iter_mut() {
         let wavemix = clipminmaxf(0.005, p.shape + lfoz, 0.995);
-        let mut sig = (1.0 - wavemix) * osc_wave_scanf(wave_table_ref(s.wave0), phi0);
+        let mut sig = (1.0 - wavemix) * osc_wave_scanf(wave0, phi0);
 
-        sig += wavemix * osc_wave_scanf(wave_table_ref(s.wave1), phi1);
+        sig += wavemix * osc_wave_scanf(wave1, phi1);
 
-        let subsig = osc_wave_scanf(wave_table_ref(s.subwave), phisub);
+        let subsig = osc_wave_scanf(subwave, phisub);
 
         sig = (1.0 - submix) * sig + submix * subsig;
         sig = (1.0 - ringmix) * sig + ringmix * (subsig * sig);
@@ -265,7 +290,7 @@ pub fn osc_cycle(raves: &mut Raves, params: &UserOscParams, yn: &mut [i32]) {
 
         sig = prelpf.process_fo(sig);
         sig += s.dither * osc_white();
-        sig = (sig * s.bitres).round() * s.bitresrcp;
+        sig = F32Ext::round(sig * s.bitres) * s.bitresrcp;
         sig = postlpf.process_fo(sig);
         sig = osc_softclipf(0.125, sig);
 
@@ -301,19 +326,17 @@ pub fn osc_param(raves: &mut Raves, index: UserOscParamId, value: u16) {
     match index {
         UserOscParamId::Id1 => {
             // Wave 0
-            let cnt : usize = K_WAVES_A_CNT + K_WAVES_B_CNT + K_WAVES_C_CNT;
-            p.wave0 = (value % cnt as u16) as u8;
+            p.wave0 = (value % WAVE0_CNT as u16) as u8;
             s.flags |= RavesFlags::Wave0 as u8;
         },
         UserOscParamId::Id2 => {
             // Wave 1
-            let cnt : usize = K_WAVES_D_CNT + K_WAVES_E_CNT + K_WAVES_F_CNT;
-            p.wave1 = (value % cnt as u16) as u8;
+            p.wave1 = (value % WAVE1_CNT as u16) as u8;
             s.flags |= RavesFlags::Wave1 as u8;
         },
         UserOscParamId::Id3 => {
             // Sub wave
-            p.subwave = (value % K_WAVES_A_CNT as u16) as u8;
+            p.subwave = (value % SUBWAVE_CNT as u16) as u8;
             s.flags |= RavesFlags::SubWave as u8;
         },
         UserOscParamId::Id4 => {
@@ -338,3 +361,150 @@ pub fn osc_param(raves: &mut Raves, index: UserOscParamId, value: u16) {
         },
     }
 }
+
+impl Oscillator for Raves {
+    fn init(&mut self, platform: u32, api: u32) {
+        osc_init(self, platform, api);
+    }
+
+    fn cycle(&mut self, params: &UserOscParams, yn: &mut [i32]) {
+        osc_cycle(self, params, yn);
+    }
+
+    fn note_on(&mut self, params: &UserOscParams) {
+        osc_noteon(self, params);
+    }
+
+    fn param(&mut self, index: UserOscParamId, value: u16) {
+        osc_param(self, index, value);
+    }
+}
+
+// Property tests of `osc_cycle`. After every cycle, the filter states
+// and phases are checked to be in range, and the Q31 output to be within
+// the converted bound on the clipper's output.
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use logue::host::osc_rand_seed;
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// Bound on the state of both filters. Their input stays within
+    /// [-4/3, 4/3] (bit crush can round up to that), and neither has a
+    /// gain above 2, so a state outside of this is NaN, infinite or the
+    /// sign of an unstable filter.
+    const FILTER_BOUND: f32 = 4.0;
+
+    /// Bound on the output of `osc_softclipf(0.125, _)`, which clips its
+    /// input to [-1, 1] first. Beyond [-1, 1), or for NaN, the conversion
+    /// to Q31 overflows: it saturates in Rust, but is undefined in the
+    /// C++.
+    const OUTPUT_BOUND: f32 = 0.875;
+
+    prop_compose! {
+        /// Parameters as `osc_param` sets them.
+        fn raves_params()(
+            submix in 0.05f32..=0.95,
+            ringmix in 0.0f32..=1.0,
+            bitcrush in 0.0f32..=1.0,
+            shape in 0.0f32..=1.0,
+            shiftshape in 1.0f32..=2.0,
+            wave0 in 0..WAVE0_CNT as u8,
+            wave1 in 0..WAVE1_CNT as u8,
+            subwave in 0..SUBWAVE_CNT as u8,
+        ) -> RavesParams {
+            RavesParams { submix, ringmix, bitcrush, shape, shiftshape, wave0, wave1, subwave, padding: 0 }
+        }
+    }
+
+    /// What the firmware passes to one cycle, and whether a note on
+    /// comes first.
+    #[derive(Clone, Debug)]
+    struct Cycle {
+        frames: usize,
+        pitch: u16,
+        shape_lfo: i32,
+        note_on: bool,
+    }
+
+    fn cycles() -> impl Strategy<Value = Vec<Cycle>> {
+        let cycle = (1..=64usize, 0..=151u16, any::<u8>(), any::<i32>(), prop::bool::weighted(0.1))
+            .prop_map(|(frames, note, fine, shape_lfo, note_on)| Cycle {
+                frames,
+                pitch: note << 8 | fine as u16,
+                shape_lfo,
+                note_on,
+            });
+        prop::collection::vec(cycle, 1..16)
+    }
+
+    fn check_state(raves: &Raves) -> Result<(), TestCaseError> {
+        let s = &raves.state;
+        for (name, phi) in [("phi0", s.phi0), ("phi1", s.phi1), ("phisub", s.phisub)] {
+            prop_assert!((0.0..1.0).contains(&phi), "{} = {}", name, phi);
+        }
+        for (name, z1) in [("prelpf", raves.prelpf.z1()), ("postlpf", raves.postlpf.z1())] {
+            prop_assert!(z1.abs() <= FILTER_BOUND, "{} state = {}", name, z1);
+        }
+        Ok(())
+    }
+
+    /// Check that `yn` is within the converted `OUTPUT_BOUND`. The
+    /// conversion is monotonic, so samples within it came from signals
+    /// within the bound. A NaN signal converts to 0, but would leave the
+    /// state of the filter before the clipper NaN too.
+    fn check_output(yn: &[i32]) -> Result<(), TestCaseError> {
+        let bound = f32_to_q31(OUTPUT_BOUND);
+        for (i, y) in yn.iter().enumerate() {
+            prop_assert!((-bound..=bound).contains(y), "yn[{}] = {:#x}, beyond {:#x}", i, y, bound);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn output_check_rejects_extremes() {
+        let bound = f32_to_q31(OUTPUT_BOUND);
+        assert!(check_output(&[0, bound, -bound]).is_ok());
+        // `i32::MIN.abs()` wraps back to `i32::MIN` with overflow checks
+        // off, as in the release profile.
+        for y in [i32::MIN, i32::MAX, bound + 1, -bound - 1] {
+            assert!(check_output(&[0, y]).is_err(), "{:#x} passed", y);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn cycle_invariants(seed in any::<u32>(), params in raves_params(), cycles in cycles()) {
+            osc_rand_seed(seed);
+            let mut raves = Raves::new();
+            raves.init();
+            raves.params = params;
+            raves.state.flags = RavesFlags::Wave0 as u8
+                | RavesFlags::Wave1 as u8
+                | RavesFlags::SubWave as u8
+                | RavesFlags::BitCrush as u8;
+
+            let mut yn = [0; 64];
+            for cycle in &cycles {
+                let params = UserOscParams {
+                    shape_lfo: cycle.shape_lfo,
+                    pitch: cycle.pitch,
+                    cutoff: 0x1fff,
+                    resonance: 0,
+                    reserved0: [0; 3],
+                };
+                if cycle.note_on {
+                    osc_noteon(&mut raves, &params);
+                }
+                osc_cycle(&mut raves, &params, &mut yn[..cycle.frames]);
+                check_state(&raves)?;
+                check_output(&yn[..cycle.frames])?;
+            }
+        }
+    }
+}
@@ -0,0 +1,27 @@
+//! The unit's manifest. `build.rs` includes this file to check it, so it
+//! may only refer to the `logue` crate.
+
+use logue::manifest::{Manifest, Param};
+use logue::userprg::{ApiVersion, K_USER_MODULE_OSC};
+use logue::wavebank::*;
+
+/// Number of waves selectable for each oscillator.
+pub const WAVE0_CNT: usize = K_WAVES_A_CNT + K_WAVES_B_CNT + K_WAVES_C_CNT;
+pub const WAVE1_CNT: usize = K_WAVES_D_CNT + K_WAVES_E_CNT + K_WAVES_F_CNT;
+pub const SUBWAVE_CNT: usize = K_WAVES_A_CNT;
+
+pub const MANIFEST: Manifest = Manifest {
+    module: K_USER_MODULE_OSC,
+    dev_id: 0,
+    prg_id: 0,
+    version: ApiVersion::new(1, 0, 3),
+    name: "raves",
+    params: &[
+        Param::new("Wave A",    0, WAVE0_CNT as i32 - 1,   ""),
+        Param::new("Wave B",    0, WAVE1_CNT as i32 - 1,   ""),
+        Param::new("Sub Wave",  0, SUBWAVE_CNT as i32 - 1, ""),
+        Param::new("Sub Mix",   0, 100, "%"),
+        Param::new("Ring Mix",  0, 100, "%"),
+        Param::new("Bit Crush", 0, 100, "%"),
+    ],
+};
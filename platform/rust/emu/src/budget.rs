@@ -0,0 +1,121 @@
+//! How much of the real-time budget a unit's callbacks use.
+//!
+//! The firmware renders audio in blocks of 64 frames at 48 kHz, so the
+//! `cycle` callback of an oscillator, along with whatever other callbacks
+//! come before it, must return well within the 1.33 ms a block lasts: the
+//! rest of the time goes to the firmware and the effects units. A `Meter`
+//! plays a unit like any `Oscillator` and records the estimated cycles
+//! each call takes (see `cpu`), to compare with what a block allows.
+
+use logue::host::sim::{Schedule, Step, MAX_FRAMES};
+use logue::userosc::*;
+
+use crate::{Hook, Unit};
+
+/// The sample rate of all logue platforms.
+pub const SAMPLE_RATE: u64 = 48_000;
+
+/// The clock of the STM32F446 the logue synthesizers are built around,
+/// at its maximum.
+pub const DEFAULT_CLOCK_HZ: u64 = 180_000_000;
+
+/// The largest share of a block, as a percentage, a callback may take by
+/// default, leaving the rest to the firmware and the effects units.
+pub const DEFAULT_MAX_PERCENT: f64 = 50.0;
+
+/// The cycles in a block of `MAX_FRAMES` frames at a given clock.
+pub fn block_cycles(clock_hz: u64) -> u64 {
+    clock_hz * MAX_FRAMES as u64 / SAMPLE_RATE
+}
+
+/// Make every cycle of a schedule render a full block, as the firmware
+/// does, so that the cycles measured are per block.
+pub fn full_blocks(schedule: &Schedule) -> Schedule {
+    Schedule(schedule.0.iter().map(|&step| match step {
+        Step::Cycle(_) => Step::Cycle(MAX_FRAMES),
+        step => step,
+    }).collect())
+}
+
+/// The cycles taken by calls to one callback.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub calls: u64,
+    pub total: u64,
+    pub worst: u64,
+}
+
+impl Usage {
+    fn record(&mut self, cycles: u64) {
+        self.calls += 1;
+        self.total += cycles;
+        self.worst = self.worst.max(cycles);
+    }
+
+    /// The mean cycles per call, or 0 without calls.
+    pub fn mean(&self) -> u64 {
+        self.total.checked_div(self.calls).unwrap_or(0)
+    }
+}
+
+/// The callbacks in hook table order, to index `Meter::usage` with.
+pub const HOOKS: [Hook; 7] = [Hook::Init, Hook::Cycle, Hook::On, Hook::Off, Hook::Mute, Hook::Value, Hook::Param];
+
+/// Whether calls to `hook` must fit in the block budget: all but `init`
+/// do, which runs once, when the unit is loaded, outside of rendering.
+pub fn is_budgeted(hook: Hook) -> bool {
+    hook != Hook::Init
+}
+
+/// An emulated unit that records the cycles of every call.
+pub struct Meter {
+    unit: Unit,
+    usage: [Usage; 7],
+}
+
+impl Meter {
+    pub fn new(unit: Unit) -> Self {
+        Meter { unit, usage: Default::default() }
+    }
+
+    /// The cycles taken by calls to `hook` so far.
+    pub fn usage(&self, hook: Hook) -> Usage {
+        self.usage[hook as usize]
+    }
+
+    fn measure<F: FnOnce(&mut Unit)>(&mut self, hook: Hook, call: F) {
+        let start = self.unit.cycles();
+        call(&mut self.unit);
+        self.usage[hook as usize].record(self.unit.cycles() - start);
+    }
+}
+
+impl Oscillator for Meter {
+    fn init(&mut self, platform: u32, api: u32) {
+        self.measure(Hook::Init, |u| u.init(platform, api));
+    }
+
+    fn cycle(&mut self, params: &UserOscParams, yn: &mut [i32]) {
+        self.measure(Hook::Cycle, |u| u.cycle(params, yn));
+    }
+
+    fn note_on(&mut self, params: &UserOscParams) {
+        self.measure(Hook::On, |u| u.note_on(params));
+    }
+
+    fn note_off(&mut self, params: &UserOscParams) {
+        self.measure(Hook::Off, |u| u.note_off(params));
+    }
+
+    fn mute(&mut self, params: &UserOscParams) {
+        self.measure(Hook::Mute, |u| u.mute(params));
+    }
+
+    fn value(&mut self, value: u16) {
+        self.measure(Hook::Value, |u| u.value(value));
+    }
+
+    fn param(&mut self, index: UserOscParamId, value: u16) {
+        self.measure(Hook::Param, |u| u.param(index, value));
+    }
+}
@@ -0,0 +1,1489 @@
+//! An interpreter for the Thumb-2 instruction set of the Cortex-M4, with
+//! the DSP extension and the single-precision FPU (FPv4-SP).
+//!
+//! It covers the instructions compilers emit for user units, decoded as
+//! in the ARMv7-M Architecture Reference Manual, whose pseudocode names
+//! (`AddWithCarry`, `ThumbExpandImm_C`, ...) the helpers below follow.
+//! Exceptions, privileged state and the parallel add/subtract
+//! instructions are not modelled: they decode as undefined.
+//! Floating point follows the FPSCR's default settings, round to nearest
+//! with neither flush-to-zero nor default NaN, so results are those of
+//! the host's IEEE 754 arithmetic.
+//!
+//! Cycles are estimated with the instruction timings of the Cortex-M4
+//! Technical Reference Manual, for code and data in zero wait state
+//! SRAM: one cycle per instruction, plus one per data access, plus the
+//! extra cycles below. Where the manual gives a range, the upper end is
+//! taken, so the estimate errs on the side of too many cycles. Nothing
+//! is modelled that happens between instructions, such as the pipelining
+//! of neighboring loads and stores or interrupts taken by the firmware.
+
+use crate::memory::Memory;
+use crate::Fault;
+
+const SP: usize = 13;
+const LR: usize = 14;
+const PC: usize = 15;
+
+/// Cycles to refill the pipeline after a branch.
+const BRANCH_REFILL: u64 = 3;
+/// Extra cycles of MLA and MLS.
+const MULTIPLY_ACCUMULATE: u64 = 1;
+/// Extra cycles of SDIV and UDIV, which take 2 to 12 cycles depending
+/// on their operands.
+const DIVIDE: u64 = 11;
+/// Extra cycles of the FPU's multiply-accumulate instructions, fused or
+/// not.
+const FP_MULTIPLY_ACCUMULATE: u64 = 2;
+/// Extra cycles of VDIV and VSQRT.
+const FP_DIVIDE: u64 = 13;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Shift {
+    Lsl,
+    Lsr,
+    Asr,
+    Ror,
+    Rrx,
+}
+
+/// The registers of the core and FPU.
+#[derive(Clone, Debug, Default)]
+pub struct Cpu {
+    /// Core registers. `r[15]` is the address of the next instruction.
+    pub r: [u32; 16],
+    pub n: bool,
+    pub z: bool,
+    pub c: bool,
+    pub v: bool,
+    /// Sticky saturation flag.
+    pub q: bool,
+    /// FPU registers S0-S31, as bits. D<n> is S<2n> and S<2n+1>.
+    pub s: [u32; 32],
+    pub fpscr: u32,
+    /// ITSTATE: the condition and mask of the current IT block.
+    it: u8,
+    /// Address of the instruction being executed.
+    cur: u32,
+    /// Instructions executed so far, including those skipped by IT.
+    pub instructions: u64,
+    /// Estimated cycles taken so far.
+    pub cycles: u64,
+}
+
+fn bit(x: u32, n: u32) -> bool {
+    (x >> n) & 1 != 0
+}
+
+fn bits(x: u32, hi: u32, lo: u32) -> u32 {
+    (x >> lo) & ((1 << (hi - lo + 1)) - 1)
+}
+
+fn sign_extend(x: u32, width: u32) -> u32 {
+    let shift = 32 - width;
+    (((x << shift) as i32) >> shift) as u32
+}
+
+fn add_with_carry(x: u32, y: u32, carry_in: bool) -> (u32, bool, bool) {
+    let unsigned = x as u64 + y as u64 + carry_in as u64;
+    let signed = x as i32 as i64 + y as i32 as i64 + carry_in as i64;
+    let result = unsigned as u32;
+    (result, result as u64 != unsigned, result as i32 as i64 != signed)
+}
+
+fn decode_imm_shift(ty: u32, imm5: u32) -> (Shift, u32) {
+    match ty {
+        0 => (Shift::Lsl, imm5),
+        1 => (Shift::Lsr, if imm5 == 0 { 32 } else { imm5 }),
+        2 => (Shift::Asr, if imm5 == 0 { 32 } else { imm5 }),
+        _ if imm5 == 0 => (Shift::Rrx, 1),
+        _ => (Shift::Ror, imm5),
+    }
+}
+
+fn shift_c(value: u32, ty: Shift, amount: u32, carry_in: bool) -> (u32, bool) {
+    if amount == 0 && ty != Shift::Rrx {
+        return (value, carry_in);
+    }
+    match ty {
+        Shift::Lsl => match amount {
+            1..=31 => (value << amount, bit(value, 32 - amount)),
+            32 => (0, bit(value, 0)),
+            _ => (0, false),
+        },
+        Shift::Lsr => match amount {
+            1..=31 => (value >> amount, bit(value, amount - 1)),
+            32 => (0, bit(value, 31)),
+            _ => (0, false),
+        },
+        Shift::Asr => {
+            let amount = amount.min(32);
+            let result = ((value as i32 as i64) >> amount) as u32;
+            (result, bit(((value as i32 as i64) >> (amount - 1)) as u32, 0))
+        }
+        Shift::Ror => {
+            let result = value.rotate_right(amount % 32);
+            (result, bit(result, 31))
+        }
+        Shift::Rrx => ((carry_in as u32) << 31 | value >> 1, bit(value, 0)),
+    }
+}
+
+fn thumb_expand_imm_c(imm12: u32, carry_in: bool) -> (u32, bool) {
+    if bits(imm12, 11, 10) == 0 {
+        let b = imm12 & 0xff;
+        let value = match bits(imm12, 9, 8) {
+            0 => b,
+            1 => b << 16 | b,
+            2 => b << 24 | b << 8,
+            _ => b * 0x0101_0101,
+        };
+        (value, carry_in)
+    } else {
+        let value = (0x80 | (imm12 & 0x7f)).rotate_right(bits(imm12, 11, 7));
+        (value, bit(value, 31))
+    }
+}
+
+fn vfp_expand_imm(imm8: u32) -> u32 {
+    let sign = bit(imm8, 7) as u32;
+    let b6 = bit(imm8, 6) as u32;
+    let exp = (b6 ^ 1) << 7 | (if b6 == 1 { 0x1f } else { 0 }) << 2 | bits(imm8, 5, 4);
+    sign << 31 | exp << 23 | bits(imm8, 3, 0) << 19
+}
+
+/// Saturate `x` to a signed `n`-bit value, and whether it was.
+fn signed_sat(x: i64, n: u32) -> (u32, bool) {
+    let max = (1i64 << (n - 1)) - 1;
+    let min = -(1i64 << (n - 1));
+    if x > max {
+        (max as u32, true)
+    } else if x < min {
+        (min as u32, true)
+    } else {
+        (x as u32, false)
+    }
+}
+
+/// Saturate `x` to an unsigned `n`-bit value, and whether it was.
+fn unsigned_sat(x: i64, n: u32) -> (u32, bool) {
+    let max = (1i64 << n) - 1;
+    if x > max {
+        (max as u32, true)
+    } else if x < 0 {
+        (0, true)
+    } else {
+        (x as u32, false)
+    }
+}
+
+impl Cpu {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The address of the last instruction executed.
+    pub fn pc(&self) -> u32 {
+        self.cur
+    }
+
+    fn undefined(&self, insn: u32) -> Fault {
+        Fault::Undefined { pc: self.cur, insn }
+    }
+
+    /// Read a core register as an instruction does: the PC reads as the
+    /// address of the current instruction plus 4.
+    fn reg(&self, n: u32) -> u32 {
+        if n as usize == PC { self.cur.wrapping_add(4) } else { self.r[n as usize] }
+    }
+
+    /// `Align(PC, 4)`, the base of PC-relative loads.
+    fn pc_aligned(&self) -> u32 {
+        self.reg(PC as u32) & !3
+    }
+
+    fn branch(&mut self, addr: u32) {
+        self.cycles += BRANCH_REFILL;
+        self.r[PC] = addr & !1;
+    }
+
+    /// `BXWritePC`: a branch that must stay in Thumb state.
+    fn bx_write_pc(&mut self, addr: u32) -> Result<(), Fault> {
+        if addr & 1 == 0 {
+            return Err(Fault::ArmState { pc: self.cur, target: addr });
+        }
+        self.branch(addr);
+        Ok(())
+    }
+
+    /// Write the result of a data-processing instruction, for which
+    /// writing the PC is a branch.
+    fn set_reg(&mut self, n: u32, value: u32) {
+        if n as usize == PC {
+            self.branch(value);
+        } else {
+            self.r[n as usize] = value;
+        }
+    }
+
+    /// Write a loaded value, for which writing the PC is an interworking
+    /// branch.
+    fn load_reg(&mut self, n: u32, value: u32) -> Result<(), Fault> {
+        if n as usize == PC {
+            self.bx_write_pc(value)
+        } else {
+            self.r[n as usize] = value;
+            Ok(())
+        }
+    }
+
+    fn set_nz(&mut self, result: u32) {
+        self.n = bit(result, 31);
+        self.z = result == 0;
+    }
+
+    fn set_nzcv(&mut self, result: u32, c: bool, v: bool) {
+        self.set_nz(result);
+        self.c = c;
+        self.v = v;
+    }
+
+    fn condition_passed(&self, cond: u32) -> bool {
+        let result = match cond >> 1 {
+            0 => self.z,
+            1 => self.c,
+            2 => self.n,
+            3 => self.v,
+            4 => self.c && !self.z,
+            5 => self.n == self.v,
+            6 => !self.z && self.n == self.v,
+            _ => true,
+        };
+        if cond & 1 == 1 && cond != 0xf { !result } else { result }
+    }
+
+    fn in_it_block(&self) -> bool {
+        self.it & 0xf != 0
+    }
+
+    fn advance_it(&mut self) {
+        if self.it & 0x7 == 0 {
+            self.it = 0;
+        } else {
+            self.it = (self.it & 0xe0) | ((self.it << 1) & 0x1f);
+        }
+    }
+
+    /// The flags as they appear in the APSR.
+    pub fn apsr(&self) -> u32 {
+        (self.n as u32) << 31 | (self.z as u32) << 30 | (self.c as u32) << 29
+            | (self.v as u32) << 28 | (self.q as u32) << 27
+    }
+
+    fn set_apsr_nzcvq(&mut self, value: u32) {
+        self.n = bit(value, 31);
+        self.z = bit(value, 30);
+        self.c = bit(value, 29);
+        self.v = bit(value, 28);
+        self.q = bit(value, 27);
+    }
+
+    fn sf(&self, n: u32) -> f32 {
+        f32::from_bits(self.s[n as usize])
+    }
+
+    fn set_sf(&mut self, n: u32, value: f32) {
+        self.s[n as usize] = value.to_bits();
+    }
+
+    /// Execute one instruction.
+    pub fn step(&mut self, mem: &mut Memory) -> Result<(), Fault> {
+        let pc = self.r[PC];
+        let hw1 = mem.read_u16(pc)? as u32;
+        let wide = hw1 >> 11 >= 0b11101;
+        let hw2 = if wide { mem.read_u16(pc.wrapping_add(2))? as u32 } else { 0 };
+        self.cur = pc;
+        self.r[PC] = pc.wrapping_add(if wide { 4 } else { 2 });
+        self.instructions += 1;
+        self.cycles += 1;
+
+        let in_it = self.in_it_block();
+        if in_it && !self.condition_passed((self.it >> 4) as u32) {
+            self.advance_it();
+            return Ok(());
+        }
+        let accesses = mem.accesses();
+        if wide {
+            self.exec32(mem, hw1, hw2)?;
+        } else {
+            self.exec16(mem, hw1)?;
+        }
+        self.cycles += mem.accesses() - accesses;
+        if in_it {
+            self.advance_it();
+        }
+        Ok(())
+    }
+
+    fn load(&self, mem: &Memory, addr: u32, size: u32, signed: bool) -> Result<u32, Fault> {
+        Ok(match (size, signed) {
+            (1, false) => mem.read_u8(addr)? as u32,
+            (1, true) => mem.read_u8(addr)? as i8 as u32,
+            (2, false) => mem.read_u16(addr)? as u32,
+            (2, true) => mem.read_u16(addr)? as i16 as u32,
+            _ => mem.read_u32(addr)?,
+        })
+    }
+
+    fn store(&self, mem: &mut Memory, addr: u32, size: u32, value: u32) -> Result<(), Fault> {
+        match size {
+            1 => mem.write_u8(addr, value as u8),
+            2 => mem.write_u16(addr, value as u16),
+            _ => mem.write_u32(addr, value),
+        }
+    }
+
+    /// LDM/POP: load the registers in `list` from consecutive words.
+    fn load_multiple(&mut self, mem: &Memory, mut addr: u32, list: u32) -> Result<(), Fault> {
+        let mut new_pc = None;
+        for i in 0..16 {
+            if bit(list, i) {
+                let value = mem.read_u32(addr)?;
+                if i as usize == PC {
+                    new_pc = Some(value);
+                } else {
+                    self.r[i as usize] = value;
+                }
+                addr = addr.wrapping_add(4);
+            }
+        }
+        match new_pc {
+            Some(pc) => self.bx_write_pc(pc),
+            None => Ok(()),
+        }
+    }
+
+    /// STM/PUSH: store the registers in `list` to consecutive words.
+    fn store_multiple(&mut self, mem: &mut Memory, mut addr: u32, list: u32) -> Result<(), Fault> {
+        for i in 0..16 {
+            if bit(list, i) {
+                mem.write_u32(addr, self.reg(i))?;
+                addr = addr.wrapping_add(4);
+            }
+        }
+        Ok(())
+    }
+
+    fn exec16(&mut self, mem: &mut Memory, op: u32) -> Result<(), Fault> {
+        let setflags = !self.in_it_block();
+        match op >> 10 {
+            // Shift (immediate), add, subtract, move and compare.
+            0b000000..=0b001111 => {
+                let rd = op & 7;
+                let rn = bits(op, 5, 3);
+                match bits(op, 13, 11) {
+                    0b000..=0b010 => {
+                        let (ty, amount) = decode_imm_shift(bits(op, 12, 11), bits(op, 10, 6));
+                        let (result, c) = shift_c(self.r[rn as usize], ty, amount, self.c);
+                        self.r[rd as usize] = result;
+                        if setflags {
+                            self.set_nz(result);
+                            self.c = c;
+                        }
+                    }
+                    0b011 => {
+                        let operand = if bit(op, 10) { bits(op, 8, 6) } else { self.r[bits(op, 8, 6) as usize] };
+                        let x = self.r[rn as usize];
+                        let (result, c, v) = if bit(op, 9) {
+                            add_with_carry(x, !operand, true)
+                        } else {
+                            add_with_carry(x, operand, false)
+                        };
+                        self.r[rd as usize] = result;
+                        if setflags {
+                            self.set_nzcv(result, c, v);
+                        }
+                    }
+                    opc => {
+                        let rdn = bits(op, 10, 8) as usize;
+                        let imm = op & 0xff;
+                        match opc {
+                            0b100 => {
+                                self.r[rdn] = imm;
+                                if setflags {
+                                    self.set_nz(imm);
+                                }
+                            }
+                            0b101 => {
+                                let (result, c, v) = add_with_carry(self.r[rdn], !imm, true);
+                                self.set_nzcv(result, c, v);
+                            }
+                            _ => {
+                                let (result, c, v) = if opc == 0b110 {
+                                    add_with_carry(self.r[rdn], imm, false)
+                                } else {
+                                    add_with_carry(self.r[rdn], !imm, true)
+                                };
+                                self.r[rdn] = result;
+                                if setflags {
+                                    self.set_nzcv(result, c, v);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            // Data processing.
+            0b010000 => {
+                let rdn = (op & 7) as usize;
+                let rm = bits(op, 5, 3) as usize;
+                let (x, y) = (self.r[rdn], self.r[rm]);
+                match bits(op, 9, 6) {
+                    opc @ (0b0000 | 0b0001 | 0b1100 | 0b1110 | 0b1111 | 0b1000) => {
+                        let result = match opc {
+                            0b0000 | 0b1000 => x & y,
+                            0b0001 => x ^ y,
+                            0b1100 => x | y,
+                            0b1110 => x & !y,
+                            _ => !y,
+                        };
+                        if opc != 0b1000 {
+                            self.r[rdn] = result;
+                        }
+                        if setflags || opc == 0b1000 {
+                            self.set_nz(result);
+                        }
+                    }
+                    opc @ (0b0010 | 0b0011 | 0b0100 | 0b0111) => {
+                        let ty = match opc {
+                            0b0010 => Shift::Lsl,
+                            0b0011 => Shift::Lsr,
+                            0b0100 => Shift::Asr,
+                            _ => Shift::Ror,
+                        };
+                        let (result, c) = shift_c(x, ty, y & 0xff, self.c);
+                        self.r[rdn] = result;
+                        if setflags {
+                            self.set_nz(result);
+                            self.c = c;
+                        }
+                    }
+                    opc @ (0b0101 | 0b0110 | 0b1001 | 0b1010 | 0b1011) => {
+                        let (result, c, v) = match opc {
+                            0b0101 => add_with_carry(x, y, self.c),
+                            0b0110 => add_with_carry(x, !y, self.c),
+                            0b1001 => add_with_carry(!y, 0, true),
+                            0b1010 => add_with_carry(x, !y, true),
+                            _ => add_with_carry(x, y, false),
+                        };
+                        let compare = opc == 0b1010 || opc == 0b1011;
+                        if !compare {
+                            self.r[rdn] = result;
+                        }
+                        if setflags || compare {
+                            self.set_nzcv(result, c, v);
+                        }
+                    }
+                    _ => {
+                        // MUL
+                        let result = x.wrapping_mul(y);
+                        self.r[rdn] = result;
+                        if setflags {
+                            self.set_nz(result);
+                        }
+                    }
+                }
+            }
+            // Special data processing, and branch and exchange.
+            0b010001 => {
+                let rdn = (bit(op, 7) as u32) << 3 | (op & 7);
+                let rm = bits(op, 6, 3);
+                match bits(op, 9, 8) {
+                    0b00 => {
+                        let result = self.reg(rdn).wrapping_add(self.reg(rm));
+                        self.set_reg(rdn, result);
+                    }
+                    0b01 => {
+                        let (result, c, v) = add_with_carry(self.reg(rdn), !self.reg(rm), true);
+                        self.set_nzcv(result, c, v);
+                    }
+                    0b10 => {
+                        let value = self.reg(rm);
+                        self.set_reg(rdn, value);
+                    }
+                    _ => {
+                        let target = self.reg(rm);
+                        if bit(op, 7) {
+                            self.r[LR] = self.r[PC] | 1;
+                        }
+                        self.bx_write_pc(target)?;
+                    }
+                }
+            }
+            // LDR (literal)
+            0b010010 | 0b010011 => {
+                let addr = self.pc_aligned().wrapping_add((op & 0xff) * 4);
+                self.r[bits(op, 10, 8) as usize] = mem.read_u32(addr)?;
+            }
+            // Load/store single data item, register offset.
+            0b010100..=0b010111 => {
+                let rt = op & 7;
+                let addr = self.r[bits(op, 5, 3) as usize].wrapping_add(self.r[bits(op, 8, 6) as usize]);
+                match bits(op, 11, 9) {
+                    0 => self.store(mem, addr, 4, self.r[rt as usize])?,
+                    1 => self.store(mem, addr, 2, self.r[rt as usize])?,
+                    2 => self.store(mem, addr, 1, self.r[rt as usize])?,
+                    opb => {
+                        let (size, signed) = match opb {
+                            3 => (1, true),
+                            4 => (4, false),
+                            5 => (2, false),
+                            6 => (1, false),
+                            _ => (2, true),
+                        };
+                        self.r[rt as usize] = self.load(mem, addr, size, signed)?;
+                    }
+                }
+            }
+            // Load/store word or byte, and halfword, immediate offset.
+            0b011000..=0b100011 => {
+                let rt = (op & 7) as usize;
+                let imm5 = bits(op, 10, 6);
+                let size = match op >> 12 {
+                    0b0110 => 4,
+                    0b0111 => 1,
+                    _ => 2,
+                };
+                let addr = self.r[bits(op, 5, 3) as usize].wrapping_add(imm5 * size);
+                if bit(op, 11) {
+                    self.r[rt] = self.load(mem, addr, size, false)?;
+                } else {
+                    self.store(mem, addr, size, self.r[rt])?;
+                }
+            }
+            // Load/store SP-relative.
+            0b100100..=0b100111 => {
+                let rt = bits(op, 10, 8) as usize;
+                let addr = self.r[SP].wrapping_add((op & 0xff) * 4);
+                if bit(op, 11) {
+                    self.r[rt] = mem.read_u32(addr)?;
+                } else {
+                    mem.write_u32(addr, self.r[rt])?;
+                }
+            }
+            // ADR
+            0b101000 | 0b101001 => {
+                self.r[bits(op, 10, 8) as usize] = self.pc_aligned().wrapping_add((op & 0xff) * 4);
+            }
+            // ADD (SP plus immediate)
+            0b101010 | 0b101011 => {
+                self.r[bits(op, 10, 8) as usize] = self.r[SP].wrapping_add((op & 0xff) * 4);
+            }
+            0b101100..=0b101111 => self.exec16_misc(mem, op)?,
+            // STM
+            0b110000 | 0b110001 => {
+                let rn = bits(op, 10, 8) as usize;
+                let list = op & 0xff;
+                let addr = self.r[rn];
+                self.store_multiple(mem, addr, list)?;
+                self.r[rn] = addr.wrapping_add(4 * list.count_ones());
+            }
+            // LDM
+            0b110010 | 0b110011 => {
+                let rn = bits(op, 10, 8) as usize;
+                let list = op & 0xff;
+                let addr = self.r[rn];
+                self.load_multiple(mem, addr, list)?;
+                if !bit(list, rn as u32) {
+                    self.r[rn] = addr.wrapping_add(4 * list.count_ones());
+                }
+            }
+            // Conditional branch, and supervisor call.
+            0b110100..=0b110111 => {
+                let cond = bits(op, 11, 8);
+                if cond >= 0b1110 {
+                    return Err(self.undefined(op));
+                }
+                if self.condition_passed(cond) {
+                    let target = self.reg(PC as u32).wrapping_add(sign_extend((op & 0xff) << 1, 9));
+                    self.branch(target);
+                }
+            }
+            // Unconditional branch.
+            0b111000 | 0b111001 => {
+                let target = self.reg(PC as u32).wrapping_add(sign_extend((op & 0x7ff) << 1, 12));
+                self.branch(target);
+            }
+            _ => return Err(self.undefined(op)),
+        }
+        Ok(())
+    }
+
+    fn exec16_misc(&mut self, mem: &mut Memory, op: u32) -> Result<(), Fault> {
+        match bits(op, 11, 8) {
+            0b0000 => {
+                let imm = (op & 0x7f) * 4;
+                self.r[SP] = if bit(op, 7) { self.r[SP].wrapping_sub(imm) } else { self.r[SP].wrapping_add(imm) };
+            }
+            0b0001 | 0b0011 | 0b1001 | 0b1011 => {
+                // CBZ, CBNZ
+                let rn = (op & 7) as usize;
+                let offset = (bit(op, 9) as u32) << 6 | bits(op, 7, 3) << 1;
+                if (self.r[rn] == 0) != bit(op, 11) {
+                    let target = self.reg(PC as u32).wrapping_add(offset);
+                    self.branch(target);
+                }
+            }
+            0b0010 => {
+                let rm = self.r[bits(op, 5, 3) as usize];
+                self.r[(op & 7) as usize] = match bits(op, 7, 6) {
+                    0 => rm as i16 as u32,
+                    1 => rm as i8 as u32,
+                    2 => rm & 0xffff,
+                    _ => rm & 0xff,
+                };
+            }
+            0b0100 | 0b0101 => {
+                // PUSH
+                let list = (op & 0xff) | (bit(op, 8) as u32) << LR;
+                let addr = self.r[SP].wrapping_sub(4 * list.count_ones());
+                self.store_multiple(mem, addr, list)?;
+                self.r[SP] = addr;
+            }
+            // CPS: interrupts are not modelled.
+            0b0110 if bits(op, 7, 5) == 0b011 => {}
+            0b1010 if bits(op, 7, 6) != 0b10 => {
+                let rm = self.r[bits(op, 5, 3) as usize];
+                self.r[(op & 7) as usize] = match bits(op, 7, 6) {
+                    0 => rm.swap_bytes(),
+                    1 => (rm & 0x00ff_00ff) << 8 | (rm & 0xff00_ff00) >> 8,
+                    _ => (rm as u16).swap_bytes() as i16 as u32,
+                };
+            }
+            0b1100 | 0b1101 => {
+                // POP
+                let list = (op & 0xff) | (bit(op, 8) as u32) << PC;
+                let addr = self.r[SP];
+                self.r[SP] = addr.wrapping_add(4 * list.count_ones());
+                self.load_multiple(mem, addr, list)?;
+            }
+            0b1110 => return Err(Fault::Breakpoint { pc: self.cur }),
+            0b1111 => {
+                if op & 0xf != 0 {
+                    self.it = (op & 0xff) as u8;
+                }
+                // Otherwise a hint (NOP, YIELD, WFE, WFI or SEV).
+            }
+            _ => return Err(self.undefined(op)),
+        }
+        Ok(())
+    }
+
+    fn exec32(&mut self, mem: &mut Memory, hw1: u32, hw2: u32) -> Result<(), Fault> {
+        let insn = hw1 << 16 | hw2;
+        match bits(hw1, 12, 11) {
+            0b01 => {
+                if hw1 & 0x0640 == 0x0000 {
+                    self.exec32_ldm_stm(mem, hw1, hw2)
+                } else if hw1 & 0x0640 == 0x0040 {
+                    self.exec32_dual(mem, hw1, hw2)
+                } else if hw1 & 0x0600 == 0x0200 {
+                    self.exec32_dp_shifted(hw1, hw2)
+                } else {
+                    self.exec32_coprocessor(mem, hw1, hw2)
+                }
+            }
+            0b10 => {
+                if bit(hw2, 15) {
+                    self.exec32_branch_misc(hw1, hw2)
+                } else if bit(hw1, 9) {
+                    self.exec32_plain_imm(hw1, hw2)
+                } else {
+                    self.exec32_modified_imm(hw1, hw2)
+                }
+            }
+            _ => {
+                let op2 = bits(hw1, 10, 4);
+                if op2 & 0b1110001 == 0b0000000 || op2 & 0b1100001 == 0b0000001 && op2 & 0b110 != 0b110 {
+                    self.exec32_load_store(mem, hw1, hw2)
+                } else if op2 & 0b1110000 == 0b0100000 {
+                    self.exec32_dp_register(hw1, hw2)
+                } else if op2 & 0b1111000 == 0b0110000 {
+                    self.exec32_multiply(hw1, hw2)
+                } else if op2 & 0b1111000 == 0b0111000 {
+                    self.exec32_long_multiply(hw1, hw2)
+                } else if op2 & 0b1000000 != 0 {
+                    self.exec32_coprocessor(mem, hw1, hw2)
+                } else {
+                    Err(self.undefined(insn))
+                }
+            }
+        }
+    }
+
+    fn exec32_ldm_stm(&mut self, mem: &mut Memory, hw1: u32, hw2: u32) -> Result<(), Fault> {
+        let rn = (hw1 & 0xf) as usize;
+        let wback = bit(hw1, 5);
+        let list = hw2 & 0xdfff;
+        let size = 4 * list.count_ones();
+        let (addr, end) = match bits(hw1, 8, 7) {
+            0b01 => (self.r[rn], self.r[rn].wrapping_add(size)),
+            0b10 => (self.r[rn].wrapping_sub(size), self.r[rn].wrapping_sub(size)),
+            _ => return Err(self.undefined(hw1 << 16 | hw2)),
+        };
+        if bit(hw1, 4) {
+            if wback && !bit(list, rn as u32) {
+                self.r[rn] = end;
+            }
+            self.load_multiple(mem, addr, list)?;
+        } else {
+            self.store_multiple(mem, addr, list)?;
+            if wback {
+                self.r[rn] = end;
+            }
+        }
+        Ok(())
+    }
+
+    /// Load/store dual or exclusive, and table branch.
+    fn exec32_dual(&mut self, mem: &mut Memory, hw1: u32, hw2: u32) -> Result<(), Fault> {
+        let rn = hw1 & 0xf;
+        let rt = bits(hw2, 15, 12);
+        let op1 = bits(hw1, 8, 7);
+        let op2 = bits(hw1, 5, 4);
+        if op1 == 0b01 && op2 == 0b01 && bits(hw2, 7, 5) == 0 {
+            // TBB, TBH
+            let rm = self.reg(hw2 & 0xf);
+            let base = self.reg(rn);
+            let offset = if bit(hw2, 4) {
+                mem.read_u16(base.wrapping_add(rm << 1))? as u32
+            } else {
+                mem.read_u8(base.wrapping_add(rm))? as u32
+            };
+            let target = self.reg(PC as u32).wrapping_add(offset * 2);
+            self.branch(target);
+            return Ok(());
+        }
+        if op1 & 0b10 == 0 && op2 & 0b10 == 0 || op1 == 0b01 {
+            // Exclusive accesses always succeed: there is one core.
+            let imm = if op1 == 0 { (hw2 & 0xff) * 4 } else { 0 };
+            let size = if op1 == 0 { 4 } else { 1 << bits(hw2, 5, 4) };
+            let addr = self.reg(rn).wrapping_add(imm);
+            if op2 & 1 == 1 {
+                let value = self.load(mem, addr, size, false)?;
+                self.r[rt as usize] = value;
+            } else {
+                let rd = if op1 == 0 { bits(hw2, 11, 8) } else { hw2 & 0xf };
+                self.store(mem, addr, size, self.r[rt as usize])?;
+                self.r[rd as usize] = 0;
+            }
+            return Ok(());
+        }
+        // LDRD, STRD
+        let rt2 = bits(hw2, 11, 8) as usize;
+        let imm = (hw2 & 0xff) * 4;
+        let (index, add, wback) = (bit(hw1, 8), bit(hw1, 7), bit(hw1, 5));
+        let base = if rn as usize == PC { self.pc_aligned() } else { self.r[rn as usize] };
+        let offset_addr = if add { base.wrapping_add(imm) } else { base.wrapping_sub(imm) };
+        let addr = if index { offset_addr } else { base };
+        if bit(hw1, 4) {
+            self.r[rt as usize] = mem.read_u32(addr)?;
+            self.r[rt2] = mem.read_u32(addr.wrapping_add(4))?;
+        } else {
+            mem.write_u32(addr, self.r[rt as usize])?;
+            mem.write_u32(addr.wrapping_add(4), self.r[rt2])?;
+        }
+        if wback {
+            self.r[rn as usize] = offset_addr;
+        }
+        Ok(())
+    }
+
+    /// The data-processing operations shared by the shifted register and
+    /// modified immediate encodings.
+    fn data_processing(&mut self, op: u32, setflags: bool, rn: u32, rd: u32, operand: u32, carry: bool) -> Result<(), Fault> {
+        let x = self.reg(rn);
+        let logical = |cpu: &mut Cpu, result: u32, write: bool| {
+            if write {
+                cpu.set_reg(rd, result);
+            }
+            if setflags {
+                cpu.set_nz(result);
+                cpu.c = carry;
+            }
+        };
+        let arithmetic = |cpu: &mut Cpu, (result, c, v): (u32, bool, bool), write: bool| {
+            if write {
+                cpu.set_reg(rd, result);
+            }
+            if setflags {
+                cpu.set_nzcv(result, c, v);
+            }
+        };
+        // Rd of 15 with flags set turns AND, EOR, ADD and SUB into TST,
+        // TEQ, CMN and CMP.
+        let test = rd == 15 && setflags;
+        match op {
+            0b0000 => logical(self, x & operand, !test),
+            0b0001 => logical(self, x & !operand, true),
+            0b0010 => logical(self, if rn == 15 { operand } else { x | operand }, true),
+            0b0011 => logical(self, if rn == 15 { !operand } else { x | !operand }, true),
+            0b0100 => logical(self, x ^ operand, !test),
+            0b1000 => arithmetic(self, add_with_carry(x, operand, false), !test),
+            0b1010 => arithmetic(self, add_with_carry(x, operand, self.c), true),
+            0b1011 => arithmetic(self, add_with_carry(x, !operand, self.c), true),
+            0b1101 => arithmetic(self, add_with_carry(x, !operand, true), !test),
+            0b1110 => arithmetic(self, add_with_carry(!x, operand, true), true),
+            _ => return Err(self.undefined(op)),
+        }
+        Ok(())
+    }
+
+    fn exec32_dp_shifted(&mut self, hw1: u32, hw2: u32) -> Result<(), Fault> {
+        let op = bits(hw1, 8, 5);
+        let rn = hw1 & 0xf;
+        let rd = bits(hw2, 11, 8);
+        let rm = self.reg(hw2 & 0xf);
+        let imm5 = bits(hw2, 14, 12) << 2 | bits(hw2, 7, 6);
+        let (ty, amount) = decode_imm_shift(bits(hw2, 5, 4), imm5);
+        if op == 0b0110 {
+            // PKHBT, PKHTB
+            let (shifted, _) = shift_c(rm, ty, amount, self.c);
+            let x = self.reg(rn);
+            let result = if bit(hw2, 5) {
+                (x & 0xffff_0000) | (shifted & 0xffff)
+            } else {
+                (shifted & 0xffff_0000) | (x & 0xffff)
+            };
+            self.set_reg(rd, result);
+            return Ok(());
+        }
+        let (operand, carry) = shift_c(rm, ty, amount, self.c);
+        self.data_processing(op, bit(hw1, 4), rn, rd, operand, carry)
+            .map_err(|_| self.undefined(hw1 << 16 | hw2))
+    }
+
+    fn exec32_modified_imm(&mut self, hw1: u32, hw2: u32) -> Result<(), Fault> {
+        let imm12 = (bit(hw1, 10) as u32) << 11 | bits(hw2, 14, 12) << 8 | (hw2 & 0xff);
+        let (operand, carry) = thumb_expand_imm_c(imm12, self.c);
+        self.data_processing(bits(hw1, 8, 5), bit(hw1, 4), hw1 & 0xf, bits(hw2, 11, 8), operand, carry)
+            .map_err(|_| self.undefined(hw1 << 16 | hw2))
+    }
+
+    fn exec32_plain_imm(&mut self, hw1: u32, hw2: u32) -> Result<(), Fault> {
+        let rn = hw1 & 0xf;
+        let rd = bits(hw2, 11, 8);
+        let imm12 = (bit(hw1, 10) as u32) << 11 | bits(hw2, 14, 12) << 8 | (hw2 & 0xff);
+        let imm5 = bits(hw2, 14, 12) << 2 | bits(hw2, 7, 6);
+        let x = self.reg(rn);
+        match bits(hw1, 8, 4) {
+            0b00000 => {
+                let base = if rn == 15 { self.pc_aligned() } else { x };
+                self.set_reg(rd, base.wrapping_add(imm12));
+            }
+            0b01010 => {
+                let base = if rn == 15 { self.pc_aligned() } else { x };
+                self.set_reg(rd, base.wrapping_sub(imm12));
+            }
+            0b00100 => self.set_reg(rd, rn << 12 | imm12),
+            0b01100 => {
+                let value = (self.reg(rd) & 0xffff) | (rn << 12 | imm12) << 16;
+                self.set_reg(rd, value);
+            }
+            op @ (0b10000 | 0b10010 | 0b11000 | 0b11010) => {
+                let ty = if bit(hw1, 5) { Shift::Asr } else { Shift::Lsl };
+                if ty == Shift::Asr && imm5 == 0 {
+                    // SSAT16, USAT16
+                    return Err(self.undefined(hw1 << 16 | hw2));
+                }
+                let (operand, _) = shift_c(x, ty, imm5, self.c);
+                let (result, saturated) = if op & 0b01000 == 0 {
+                    signed_sat(operand as i32 as i64, (hw2 & 0x1f) + 1)
+                } else {
+                    unsigned_sat(operand as i32 as i64, hw2 & 0x1f)
+                };
+                self.set_reg(rd, result);
+                self.q |= saturated;
+            }
+            op @ (0b10100 | 0b11100) => {
+                // SBFX, UBFX
+                let width = (hw2 & 0x1f) + 1;
+                let field = (x >> imm5) & (((1u64 << width) - 1) as u32);
+                let result = if op == 0b10100 { sign_extend(field, width) } else { field };
+                self.set_reg(rd, result);
+            }
+            0b10110 => {
+                // BFI, BFC
+                let msb = hw2 & 0x1f;
+                if msb >= imm5 {
+                    let width = msb - imm5 + 1;
+                    let mask = (((1u64 << width) - 1) as u32) << imm5;
+                    let source = if rn == 15 { 0 } else { x << imm5 };
+                    let value = (self.reg(rd) & !mask) | (source & mask);
+                    self.set_reg(rd, value);
+                }
+            }
+            _ => return Err(self.undefined(hw1 << 16 | hw2)),
+        }
+        Ok(())
+    }
+
+    fn exec32_branch_misc(&mut self, hw1: u32, hw2: u32) -> Result<(), Fault> {
+        let s = bit(hw1, 10) as u32;
+        let j1 = bit(hw2, 13) as u32;
+        let j2 = bit(hw2, 11) as u32;
+        let imm11 = hw2 & 0x7ff;
+        match bits(hw2, 14, 12) & 0b101 {
+            0b000 => {
+                if bits(hw1, 9, 7) != 0b111 {
+                    let offset = s << 20 | j2 << 19 | j1 << 18 | (hw1 & 0x3f) << 12 | imm11 << 1;
+                    if self.condition_passed(bits(hw1, 9, 6)) {
+                        let target = self.reg(PC as u32).wrapping_add(sign_extend(offset, 21));
+                        self.branch(target);
+                    }
+                    return Ok(());
+                }
+                match bits(hw1, 10, 4) {
+                    0b0111000 | 0b0111001 => {
+                        // MSR: only the flags of the APSR are modelled.
+                        if hw2 & 0xff <= 3 && bit(hw2, 11) {
+                            let value = self.reg(hw1 & 0xf);
+                            self.set_apsr_nzcvq(value);
+                        }
+                    }
+                    // Hints and barriers.
+                    0b0111010 | 0b0111011 => {}
+                    0b0111110 | 0b0111111 => {
+                        // MRS
+                        let value = if hw2 & 0xff <= 7 { self.apsr() } else { 0 };
+                        self.set_reg(bits(hw2, 11, 8), value);
+                    }
+                    _ => return Err(self.undefined(hw1 << 16 | hw2)),
+                }
+            }
+            link => {
+                let i1 = !(j1 ^ s) & 1;
+                let i2 = !(j2 ^ s) & 1;
+                let offset = s << 24 | i1 << 23 | i2 << 22 | (hw1 & 0x3ff) << 12 | imm11 << 1;
+                let target = self.reg(PC as u32).wrapping_add(sign_extend(offset, 25));
+                match link {
+                    0b001 => {}
+                    0b101 => self.r[LR] = self.r[PC] | 1,
+                    _ => return Err(self.undefined(hw1 << 16 | hw2)),
+                }
+                self.branch(target);
+            }
+        }
+        Ok(())
+    }
+
+    fn exec32_load_store(&mut self, mem: &mut Memory, hw1: u32, hw2: u32) -> Result<(), Fault> {
+        let rn = hw1 & 0xf;
+        let rt = bits(hw2, 15, 12);
+        let load = bit(hw1, 4);
+        let signed = bit(hw1, 8);
+        let size = 1 << bits(hw1, 6, 5);
+        let (addr, writeback) = if rn == 15 {
+            let imm = hw2 & 0xfff;
+            let base = self.pc_aligned();
+            (if bit(hw1, 7) { base.wrapping_add(imm) } else { base.wrapping_sub(imm) }, None)
+        } else if bit(hw1, 7) {
+            (self.r[rn as usize].wrapping_add(hw2 & 0xfff), None)
+        } else if bit(hw2, 11) {
+            let imm = hw2 & 0xff;
+            let (index, add, wback) = (bit(hw2, 10), bit(hw2, 9), bit(hw2, 8));
+            let base = self.r[rn as usize];
+            let offset_addr = if add { base.wrapping_add(imm) } else { base.wrapping_sub(imm) };
+            (if index { offset_addr } else { base }, if wback { Some(offset_addr) } else { None })
+        } else if bits(hw2, 11, 6) == 0 {
+            let offset = self.r[(hw2 & 0xf) as usize] << bits(hw2, 5, 4);
+            (self.r[rn as usize].wrapping_add(offset), None)
+        } else {
+            return Err(self.undefined(hw1 << 16 | hw2));
+        };
+        if load {
+            if rt == 15 && size < 4 {
+                // PLD, PLI: caches are not modelled.
+                return Ok(());
+            }
+            let value = self.load(mem, addr, size, signed)?;
+            if let Some(a) = writeback {
+                self.r[rn as usize] = a;
+            }
+            self.load_reg(rt, value)?;
+        } else {
+            self.store(mem, addr, size, self.reg(rt))?;
+            if let Some(a) = writeback {
+                self.r[rn as usize] = a;
+            }
+        }
+        Ok(())
+    }
+
+    fn exec32_dp_register(&mut self, hw1: u32, hw2: u32) -> Result<(), Fault> {
+        let insn = hw1 << 16 | hw2;
+        let op1 = bits(hw1, 7, 4);
+        let op2 = bits(hw2, 7, 4);
+        let rn = hw1 & 0xf;
+        let rd = bits(hw2, 11, 8);
+        let rm = hw2 & 0xf;
+        if op1 & 0b1000 == 0 && op2 == 0 {
+            // LSL, LSR, ASR, ROR (register)
+            let ty = match bits(op1, 2, 1) {
+                0 => Shift::Lsl,
+                1 => Shift::Lsr,
+                2 => Shift::Asr,
+                _ => Shift::Ror,
+            };
+            let (result, c) = shift_c(self.reg(rn), ty, self.reg(rm) & 0xff, self.c);
+            self.set_reg(rd, result);
+            if bit(op1, 0) {
+                self.set_nz(result);
+                self.c = c;
+            }
+            return Ok(());
+        }
+        if op1 & 0b1000 == 0 && op2 & 0b1000 != 0 {
+            // Sign and zero extension, with optional add.
+            let rotated = self.reg(rm).rotate_right(bits(hw2, 5, 4) * 8);
+            let extended = match op1 {
+                0b0000 => rotated as i16 as u32,
+                0b0001 => rotated & 0xffff,
+                0b0100 => rotated as i8 as u32,
+                0b0101 => rotated & 0xff,
+                _ => return Err(self.undefined(insn)),
+            };
+            let result = if rn == 15 { extended } else { self.reg(rn).wrapping_add(extended) };
+            self.set_reg(rd, result);
+            return Ok(());
+        }
+        if op1 & 0b1100 == 0b1000 && op2 & 0b1100 == 0b1000 {
+            let x = self.reg(rn);
+            let y = self.reg(rm);
+            let result = match (op1 & 3, op2 & 3) {
+                (0b00, op) => {
+                    // QADD, QDADD, QSUB, QDSUB
+                    let (doubled, sat1) = if op & 1 == 1 {
+                        signed_sat(2 * x as i32 as i64, 32)
+                    } else {
+                        (x, false)
+                    };
+                    let sum = if op & 2 == 0 {
+                        y as i32 as i64 + doubled as i32 as i64
+                    } else {
+                        y as i32 as i64 - doubled as i32 as i64
+                    };
+                    let (result, sat2) = signed_sat(sum, 32);
+                    self.q |= sat1 || sat2;
+                    result
+                }
+                (0b01, 0b00) => x.swap_bytes(),
+                (0b01, 0b01) => (x & 0x00ff_00ff) << 8 | (x & 0xff00_ff00) >> 8,
+                (0b01, 0b10) => x.reverse_bits(),
+                (0b01, 0b11) => (x as u16).swap_bytes() as i16 as u32,
+                (0b11, 0b00) => x.leading_zeros(),
+                _ => return Err(self.undefined(insn)),
+            };
+            self.set_reg(rd, result);
+            return Ok(());
+        }
+        Err(self.undefined(insn))
+    }
+
+    fn exec32_multiply(&mut self, hw1: u32, hw2: u32) -> Result<(), Fault> {
+        let insn = hw1 << 16 | hw2;
+        let rn = self.reg(hw1 & 0xf);
+        let rm = self.reg(hw2 & 0xf);
+        let ra = bits(hw2, 15, 12);
+        let rd = bits(hw2, 11, 8);
+        let acc = if ra == 15 { None } else { Some(self.reg(ra)) };
+        let half = |x: u32, top: bool| if top { (x >> 16) as i16 as i64 } else { x as i16 as i64 };
+        let result = match (bits(hw1, 6, 4), bits(hw2, 5, 4)) {
+            (0b000, 0b00) => {
+                if acc.is_some() {
+                    self.cycles += MULTIPLY_ACCUMULATE;
+                }
+                rn.wrapping_mul(rm).wrapping_add(acc.unwrap_or(0))
+            }
+            (0b000, 0b01) => {
+                self.cycles += MULTIPLY_ACCUMULATE;
+                self.reg(ra).wrapping_sub(rn.wrapping_mul(rm))
+            }
+            (0b001, nm) => {
+                // SMUL<x><y>, SMLA<x><y>
+                let product = half(rn, bit(nm, 1)) * half(rm, bit(nm, 0));
+                match acc {
+                    None => product as u32,
+                    Some(a) => {
+                        let sum = product + a as i32 as i64;
+                        self.q |= sum != sum as i32 as i64;
+                        sum as u32
+                    }
+                }
+            }
+            (op @ (0b010 | 0b100), x) if x & 2 == 0 => {
+                // SMUAD, SMLAD, SMUSD, SMLSD
+                let m = if bit(x, 0) { rm.rotate_right(16) } else { rm };
+                let lo = half(rn, false) * half(m, false);
+                let hi = half(rn, true) * half(m, true);
+                let sum = if op == 0b010 { lo + hi } else { lo - hi } + acc.map_or(0, |a| a as i32 as i64);
+                self.q |= sum != sum as i32 as i64;
+                sum as u32
+            }
+            (0b011, y) if y & 2 == 0 => {
+                // SMULW<y>, SMLAW<y>
+                let product = (rn as i32 as i64 * half(rm, bit(y, 0))) >> 16;
+                match acc {
+                    None => product as u32,
+                    Some(a) => {
+                        let sum = product + a as i32 as i64;
+                        self.q |= sum != sum as i32 as i64;
+                        sum as u32
+                    }
+                }
+            }
+            (op @ (0b101 | 0b110), r) if r & 2 == 0 => {
+                // SMMUL, SMMLA, SMMLS
+                let product = rn as i32 as i64 * rm as i32 as i64;
+                let a = (acc.unwrap_or(0) as i32 as i64) << 32;
+                let mut result = if op == 0b101 { a.wrapping_add(product) } else { a.wrapping_sub(product) };
+                if bit(r, 0) {
+                    result = result.wrapping_add(0x8000_0000);
+                }
+                (result >> 32) as u32
+            }
+            _ => return Err(self.undefined(insn)),
+        };
+        self.set_reg(rd, result);
+        Ok(())
+    }
+
+    fn exec32_long_multiply(&mut self, hw1: u32, hw2: u32) -> Result<(), Fault> {
+        let insn = hw1 << 16 | hw2;
+        let rn = self.reg(hw1 & 0xf);
+        let rm = self.reg(hw2 & 0xf);
+        let rdlo = bits(hw2, 15, 12);
+        let rdhi = bits(hw2, 11, 8);
+        let acc = (self.reg(rdhi) as u64) << 32 | self.reg(rdlo) as u64;
+        let result = match (bits(hw1, 6, 4), bits(hw2, 7, 4)) {
+            (0b000, 0b0000) => (rn as i32 as i64 * rm as i32 as i64) as u64,
+            (0b010, 0b0000) => rn as u64 * rm as u64,
+            (0b100, 0b0000) => (rn as i32 as i64 * rm as i32 as i64).wrapping_add(acc as i64) as u64,
+            (0b110, 0b0000) => (rn as u64 * rm as u64).wrapping_add(acc),
+            (0b110, 0b0110) => rn as u64 * rm as u64 + self.reg(rdlo) as u64 + self.reg(rdhi) as u64,
+            (op @ (0b001 | 0b011), 0b1111) => {
+                // SDIV, UDIV: division by zero gives 0 unless trapped.
+                self.cycles += DIVIDE;
+                let result = if rm == 0 {
+                    0
+                } else if op == 0b001 {
+                    (rn as i32).wrapping_div(rm as i32) as u32
+                } else {
+                    rn / rm
+                };
+                self.set_reg(bits(hw2, 11, 8), result);
+                return Ok(());
+            }
+            _ => return Err(self.undefined(insn)),
+        };
+        self.set_reg(rdlo, result as u32);
+        self.set_reg(rdhi, (result >> 32) as u32);
+        Ok(())
+    }
+
+    fn exec32_coprocessor(&mut self, mem: &mut Memory, hw1: u32, hw2: u32) -> Result<(), Fault> {
+        let insn = hw1 << 16 | hw2;
+        // Only the FPU, coprocessors 10 and 11, is present.
+        if bits(hw2, 11, 9) != 0b101 || bit(hw1, 12) {
+            return Err(self.undefined(insn));
+        }
+        if hw1 & 0xff00 == 0xee00 {
+            if bit(hw2, 4) {
+                self.exec_vfp_transfer(hw1, hw2)
+            } else {
+                self.exec_vfp_data(hw1, hw2)
+            }
+        } else if hw1 & 0xffe0 == 0xec40 {
+            // VMOV between two core registers and two singles or a double.
+            let rt = bits(hw2, 15, 12) as usize;
+            let rt2 = (hw1 & 0xf) as usize;
+            let m = if bit(hw2, 8) {
+                2 * ((bit(hw2, 5) as u32) << 4 | (hw2 & 0xf))
+            } else {
+                (hw2 & 0xf) << 1 | bit(hw2, 5) as u32
+            } as usize;
+            if m + 1 >= 32 {
+                return Err(self.undefined(insn));
+            }
+            if bit(hw1, 4) {
+                self.r[rt] = self.s[m];
+                self.r[rt2] = self.s[m + 1];
+            } else {
+                self.s[m] = self.r[rt];
+                self.s[m + 1] = self.r[rt2];
+            }
+            Ok(())
+        } else if hw1 & 0xfe00 == 0xec00 {
+            self.exec_vfp_load_store(mem, hw1, hw2)
+        } else {
+            Err(self.undefined(insn))
+        }
+    }
+
+    fn exec_vfp_load_store(&mut self, mem: &mut Memory, hw1: u32, hw2: u32) -> Result<(), Fault> {
+        let (p, u, d, w, l) = (bit(hw1, 8), bit(hw1, 7), bit(hw1, 6) as u32, bit(hw1, 5), bit(hw1, 4));
+        let rn = hw1 & 0xf;
+        let vd = bits(hw2, 15, 12);
+        let double = bit(hw2, 8);
+        let imm8 = hw2 & 0xff;
+        let first = if double { 2 * (d << 4 | vd) } else { vd << 1 | d };
+        let base = if rn == 15 { self.pc_aligned() } else { self.r[rn as usize] };
+        let (addr, words) = if p && !w {
+            // VLDR, VSTR
+            let offset = imm8 * 4;
+            (if u { base.wrapping_add(offset) } else { base.wrapping_sub(offset) }, if double { 2 } else { 1 })
+        } else if p != u {
+            // VLDM, VSTM, VPUSH, VPOP
+            let addr = if u { base } else { base.wrapping_sub(imm8 * 4) };
+            if w {
+                self.r[rn as usize] = if u { base.wrapping_add(imm8 * 4) } else { addr };
+            }
+            (addr, if double { imm8 & !1 } else { imm8 })
+        } else {
+            return Err(self.undefined(hw1 << 16 | hw2));
+        };
+        if first + words > 32 {
+            return Err(self.undefined(hw1 << 16 | hw2));
+        }
+        for i in 0..words {
+            let a = addr.wrapping_add(4 * i);
+            let reg = (first + i) as usize;
+            if l {
+                self.s[reg] = mem.read_u32(a)?;
+            } else {
+                mem.write_u32(a, self.s[reg])?;
+            }
+        }
+        Ok(())
+    }
+
+    fn exec_vfp_transfer(&mut self, hw1: u32, hw2: u32) -> Result<(), Fault> {
+        let rt = bits(hw2, 15, 12);
+        let l = bit(hw1, 4);
+        match (bit(hw2, 8), bits(hw1, 7, 5)) {
+            (false, 0b000) => {
+                let n = (hw1 & 0xf) << 1 | bit(hw2, 7) as u32;
+                if l {
+                    let value = self.s[n as usize];
+                    self.set_reg(rt, value);
+                } else {
+                    self.s[n as usize] = self.reg(rt);
+                }
+            }
+            (false, 0b111) if hw1 & 0xf == 1 => {
+                if !l {
+                    self.fpscr = self.reg(rt);
+                } else if rt == 15 {
+                    // VMRS APSR_nzcv, FPSCR
+                    self.n = bit(self.fpscr, 31);
+                    self.z = bit(self.fpscr, 30);
+                    self.c = bit(self.fpscr, 29);
+                    self.v = bit(self.fpscr, 28);
+                } else {
+                    self.r[rt as usize] = self.fpscr;
+                }
+            }
+            (true, a) if a & 0b110 == 0 => {
+                // VMOV between a core register and half of a double.
+                let n = 2 * ((bit(hw2, 7) as u32) << 4 | (hw1 & 0xf)) + bit(hw1, 5) as u32;
+                if n >= 32 {
+                    return Err(self.undefined(hw1 << 16 | hw2));
+                }
+                if l {
+                    self.r[rt as usize] = self.s[n as usize];
+                } else {
+                    self.s[n as usize] = self.reg(rt);
+                }
+            }
+            _ => return Err(self.undefined(hw1 << 16 | hw2)),
+        }
+        Ok(())
+    }
+
+    fn set_fp_flags(&mut self, a: f32, b: f32) {
+        let nzcv = if a.is_nan() || b.is_nan() {
+            0b0011
+        } else if a == b {
+            0b0110
+        } else if a < b {
+            0b1000
+        } else {
+            0b0010
+        };
+        self.fpscr = (self.fpscr & 0x0fff_ffff) | nzcv << 28;
+    }
+
+    fn exec_vfp_data(&mut self, hw1: u32, hw2: u32) -> Result<(), Fault> {
+        let insn = hw1 << 16 | hw2;
+        if bit(hw2, 8) {
+            // Double precision operations need an FPv5 FPU.
+            return Err(self.undefined(insn));
+        }
+        let d = bits(hw2, 15, 12) << 1 | bit(hw1, 6) as u32;
+        let n = (hw1 & 0xf) << 1 | bit(hw2, 7) as u32;
+        let m = (hw2 & 0xf) << 1 | bit(hw2, 5) as u32;
+        let op = bit(hw2, 6);
+        let (sd, sn, sm) = (self.sf(d), self.sf(n), self.sf(m));
+        let opc1 = (bit(hw1, 7) as u32) << 2 | bits(hw1, 5, 4);
+        let result = match (opc1, op) {
+            (0b000, false) => sd + sn * sm,
+            (0b000, true) => sd - sn * sm,
+            (0b001, false) => -sd + sn * sm,
+            (0b001, true) => -sd - sn * sm,
+            (0b010, false) => sn * sm,
+            (0b010, true) => -(sn * sm),
+            (0b011, false) => sn + sm,
+            (0b011, true) => sn - sm,
+            (0b100, false) => sn / sm,
+            (0b101, false) => sn.mul_add(sm, -sd),
+            (0b101, true) => (-sn).mul_add(sm, -sd),
+            (0b110, false) => sn.mul_add(sm, sd),
+            (0b110, true) => (-sn).mul_add(sm, sd),
+            (0b111, _) => return self.exec_vfp_other(hw1, hw2, d, m),
+            _ => return Err(self.undefined(insn)),
+        };
+        self.cycles += match opc1 {
+            0b000 | 0b001 | 0b101 | 0b110 => FP_MULTIPLY_ACCUMULATE,
+            0b100 => FP_DIVIDE,
+            _ => 0,
+        };
+        self.set_sf(d, result);
+        Ok(())
+    }
+
+    fn exec_vfp_other(&mut self, hw1: u32, hw2: u32, d: u32, m: u32) -> Result<(), Fault> {
+        let insn = hw1 << 16 | hw2;
+        let sm = self.sf(m);
+        if !bit(hw2, 6) {
+            // VMOV (immediate)
+            self.s[d as usize] = vfp_expand_imm((hw1 & 0xf) << 4 | (hw2 & 0xf));
+            return Ok(());
+        }
+        let opc2 = hw1 & 0xf;
+        let top = bit(hw2, 7);
+        match opc2 {
+            0b0000 => {
+                self.s[d as usize] = if top { self.s[m as usize] & 0x7fff_ffff } else { self.s[m as usize] };
+            }
+            0b0001 if !top => self.s[d as usize] = self.s[m as usize] ^ 0x8000_0000,
+            0b0001 => {
+                self.cycles += FP_DIVIDE;
+                self.set_sf(d, sm.sqrt());
+            }
+            0b0100 => self.set_fp_flags(self.sf(d), sm),
+            0b0101 => self.set_fp_flags(self.sf(d), 0.0),
+            0b1000 => {
+                // VCVT from integer: bit 7 selects signed.
+                let value = if top { self.s[m as usize] as i32 as f32 } else { self.s[m as usize] as f32 };
+                self.set_sf(d, value);
+            }
+            0b1100 | 0b1101 => {
+                // VCVT to integer: bit 7 selects rounding toward zero over
+                // the FPSCR's mode, which is kept at round to nearest.
+                let x = if top { sm } else { sm.round_ties_even() };
+                self.s[d as usize] = if opc2 & 1 == 1 { x as i32 as u32 } else { x as u32 };
+            }
+            _ => return Err(self.undefined(insn)),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run Thumb code, assembled with `llvm-mc`, from address 0 up to a
+    /// `BKPT`.
+    fn run(code: &[u8]) -> Cpu {
+        let mut mem = Memory::new();
+        mem.map("code", 0, 0x100, false);
+        mem.load(0, code).unwrap();
+        mem.map("stack", 0x1000, 0x100, true);
+        let mut cpu = Cpu::new();
+        cpu.r[SP] = 0x1100;
+        loop {
+            match cpu.step(&mut mem) {
+                Ok(()) => {}
+                Err(Fault::Breakpoint { .. }) => return cpu,
+                Err(fault) => panic!("{}", fault),
+            }
+        }
+    }
+
+    #[test]
+    fn integer_instructions() {
+        let cpu = run(&[
+            0x40, 0xf2, 0x64, 0x00, // movw r0, #100
+            0x07, 0x21,             // movs r1, #7
+            0xb0, 0xfb, 0xf1, 0xf2, // udiv r2, r0, r1
+            0x91, 0xfb, 0xf1, 0xf3, // sdiv r3, r1, r1
+            0x02, 0xfb, 0x11, 0x04, // mls r4, r2, r1, r0
+            0x02, 0x2c,             // cmp r4, #2
+            0x0c, 0xbf,             // ite eq
+            0x01, 0x25,             // moveq r5, #1
+            0x02, 0x25,             // movne r5, #2
+            0x00, 0xf3, 0x07, 0x06, // ssat r6, #8, r0
+            0x81, 0xf3, 0x84, 0x07, // usat r7, #4, r1, lsl #2
+            0xc0, 0xf3, 0x82, 0x08, // ubfx r8, r0, #2, #3
+            0x61, 0xf3, 0x06, 0x19, // bfi r9, r1, #4, #3
+            0x80, 0xfb, 0x00, 0xab, // smull r10, r11, r0, r0
+            0xb0, 0xfa, 0x80, 0xfc, // clz r12, r0
+            0x90, 0xfa, 0xa0, 0xf0, // rbit r0, r0
+            0x4f, 0xf0, 0xff, 0x21, // mov.w r1, #0xff00ff00
+            0x41, 0xfa, 0x91, 0xf2, // sxtab r2, r1, r1, ror #8
+            0x00, 0xbe,             // bkpt #0
+        ]);
+        assert_eq!(cpu.r[..13], [0x2600_0000, 0xff00_ff00, 0xff00_feff, 1, 2, 1, 100, 15, 1, 0x70, 10000, 0, 25]);
+        assert!(cpu.q, "USAT saturated");
+        assert_eq!(cpu.cycles, 42);
+    }
+
+    #[test]
+    fn float_instructions() {
+        let cpu = run(&[
+            0xb7, 0xee, 0x08, 0x0a, // vmov.f32 s0, #1.5
+            0xfd, 0xee, 0x00, 0x0a, // vmov.f32 s1, #-0.25
+            0xbd, 0xee, 0x40, 0x1a, // vcvtr.s32.f32 s2, s0
+            0xfd, 0xee, 0xe0, 0x1a, // vcvt.s32.f32 s3, s1
+            0xa0, 0xee, 0x20, 0x2a, // vfma.f32 s4, s0, s1
+            0xf1, 0xee, 0xc0, 0x2a, // vsqrt.f32 s5, s0
+            0xb4, 0xee, 0x60, 0x0a, // vcmp.f32 s0, s1
+            0xf1, 0xee, 0x10, 0xfa, // vmrs APSR_nzcv, fpscr
+            0x00, 0xbe,             // bkpt #0
+        ]);
+        assert_eq!(cpu.sf(0), 1.5);
+        assert_eq!(cpu.sf(1), -0.25);
+        assert_eq!(cpu.s[2], 2, "ties round to even");
+        assert_eq!(cpu.s[3], 0, "rounds toward zero");
+        assert_eq!(cpu.sf(4), -0.375);
+        assert_eq!(cpu.sf(5), 1.5f32.sqrt());
+        assert_eq!((cpu.n, cpu.z, cpu.c, cpu.v), (false, false, true, false));
+    }
+
+    #[test]
+    fn cycle_estimates() {
+        let cpu = run(&[
+            0x03, 0x20,             // movs r0, #3          1
+            0x03, 0xb4,             // loop: push {r0, r1}  3
+            0x0c, 0xbc,             // pop {r2, r3}         3
+            0x01, 0x38,             // subs r0, #1          1
+            0xfb, 0xd1,             // bne loop             4 taken, 1 not
+            0x80, 0xee, 0x81, 0x0a, // vdiv.f32 s0, s1, s2  14
+            0x00, 0xee, 0x81, 0x0a, // vmla.f32 s0, s1, s2  3
+            0x00, 0xbe,             // bkpt #0              1
+        ]);
+        assert_eq!(cpu.r[2], 1);
+        assert_eq!(cpu.cycles, 1 + 3 * 7 + 2 * 4 + 1 + 14 + 3 + 1);
+    }
+}
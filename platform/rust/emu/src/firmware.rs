@@ -0,0 +1,139 @@
+//! The parts of the firmware a unit can reach: the symbols listed in the
+//! `osc_api.syms` file of its platform, at the same addresses.
+//!
+//! The lookup tables and wave banks are those of `logue::host`, and the
+//! noise sources and MCU hash are serviced by its stand-ins, so that an
+//! emulated unit draws the same noise as one built for the host when
+//! both are seeded with `osc_rand_seed`. Symbols without a stand-in are
+//! left unmapped: a unit that uses them faults on the first access.
+
+use logue::host;
+use logue::platform::*;
+use logue::wavebank::*;
+
+use crate::memory::Memory;
+
+/// Where the tables the wave banks point to are placed. The firmware
+/// keeps them next to the banks, but only the pointers are at fixed
+/// addresses.
+const WAVE_TABLES: u32 = 0x0810_0000;
+
+/// A firmware function, serviced on the host when the unit calls it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Service {
+    /// `_osc_rand`, returning a `u32` in r0.
+    Rand,
+    /// `_osc_white`, returning an `f32` in s0.
+    White,
+    /// `_osc_mcu_hash`, returning a `u32` in r0.
+    McuHash,
+}
+
+/// The symbols of a platform's firmware, as `(name, address)` pairs.
+pub fn symbols(platform: u32) -> Option<Vec<(&'static str, u32)>> {
+    let syms = match platform {
+        K_USER_TARGET_PROLOGUE => include_str!("../../logue/scripts/prologue/osc_api.syms"),
+        K_USER_TARGET_MINILOGUEXD => include_str!("../../logue/scripts/minilogue-xd/osc_api.syms"),
+        K_USER_TARGET_NUTEKTDIGITAL => include_str!("../../logue/scripts/nutekt-digital/osc_api.syms"),
+        _ => return None,
+    };
+    Some(syms.lines().filter_map(parse_symbol).collect())
+}
+
+/// Parse a `name = 0x...;` line.
+fn parse_symbol(line: &'static str) -> Option<(&'static str, u32)> {
+    let (name, addr) = line.split_once('=')?;
+    let addr = addr.trim().trim_end_matches(';').trim_start_matches("0x");
+    Some((name.trim(), u32::from_str_radix(addr, 16).ok()?))
+}
+
+/// A wave bank: its symbol, the accessor for its tables and their count.
+type Bank = (&'static str, fn(usize) -> *const WaveLUT, usize);
+
+fn f32_bytes(values: &[f32]) -> Vec<u8> {
+    values.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn u32_bytes(values: &[u32]) -> Vec<u8> {
+    values.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+/// Map the firmware of `platform` into `mem`, reporting `api` as its API
+/// version, and return the functions to service at each address.
+///
+/// Returns `None` for an unknown platform.
+pub fn map(mem: &mut Memory, platform: u32, api: u32) -> Option<Vec<(u32, Service)>> {
+    let banks: [Bank; 6] = [
+        ("wavesA", get_waves_a_elt, K_WAVES_A_CNT),
+        ("wavesB", get_waves_b_elt, K_WAVES_B_CNT),
+        ("wavesC", get_waves_c_elt, K_WAVES_C_CNT),
+        ("wavesD", get_waves_d_elt, K_WAVES_D_CNT),
+        ("wavesE", get_waves_e_elt, K_WAVES_E_CNT),
+        ("wavesF", get_waves_f_elt, K_WAVES_F_CNT),
+    ];
+    let table_size = (K_WAVES_LUT_SIZE * 4) as u32;
+    let table_count: usize = banks.iter().map(|b| b.2).sum();
+    mem.map("wave tables", WAVE_TABLES, table_count as u32 * table_size, false);
+
+    let mut traps = Vec::new();
+    let mut next_table = WAVE_TABLES;
+    for (name, addr) in symbols(platform)? {
+        let data = match name {
+            "k_osc_api_version" => u32_bytes(&[api]),
+            "k_osc_api_platform" => u32_bytes(&[platform]),
+            "midi_to_hz_lut_f" => f32_bytes(&host::midi_to_hz_lut_f),
+            "bitres_lut_f" => f32_bytes(&host::bitres_lut_f),
+            "tanpi_lut_f" => f32_bytes(&host::tanpi_lut_f),
+            "_osc_rand" | "_osc_white" | "_osc_mcu_hash" => {
+                let service = match name {
+                    "_osc_rand" => Service::Rand,
+                    "_osc_white" => Service::White,
+                    _ => Service::McuHash,
+                };
+                traps.push((addr & !1, service));
+                continue;
+            }
+            _ => match banks.iter().find(|b| b.0 == name) {
+                Some(&(_, elt, count)) => {
+                    let mut pointers = Vec::new();
+                    for i in 0..count {
+                        // Safety: the host wave banks hold `count` valid
+                        // table pointers.
+                        let table = unsafe { wave_table_ref(elt(i)) };
+                        mem.load(next_table, &f32_bytes(table)).unwrap();
+                        pointers.push(next_table);
+                        next_table += table_size;
+                    }
+                    u32_bytes(&pointers)
+                }
+                None => continue,
+            },
+        };
+        mem.map(name, addr, data.len() as u32, false);
+        mem.load(addr, &data).unwrap();
+    }
+    Some(traps)
+}
+
+impl Service {
+    /// Call the host stand-in, returning the value and whether it is
+    /// returned in s0 rather than r0, as the hard-float ABI does for
+    /// `f32`.
+    pub fn call(self) -> (u32, bool) {
+        match self {
+            Service::Rand => (host::_osc_rand(), false),
+            Service::White => (host::_osc_white().to_bits(), true),
+            Service::McuHash => (host::_osc_mcu_hash(), false),
+        }
+    }
+
+    /// Rough cycles the firmware takes to run the function, including
+    /// the return, from the instructions its C source compiles to.
+    pub fn cycles(self) -> u64 {
+        match self {
+            Service::Rand => 12,
+            Service::White => 45,
+            Service::McuHash => 12,
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! Run the `payload.bin` of an oscillator unit on an emulated Cortex-M4.
+//!
+//! Host builds of a unit check its DSP code, but not the binary that
+//! ships: link-time optimization, the optimization level, code generated
+//! for the FPU, and the layout the linker script imposes can all make
+//! the payload behave differently from the same source built for the
+//! host. A `Unit` loads the payload where the firmware does, maps the
+//! firmware symbols it links against (see `firmware`), and calls the
+//! callbacks of its hook table on an interpreter of the instruction set
+//! (see `cpu`). It implements `Oscillator`, so the host tools that play
+//! units, such as `logue::host::sim` and `logue-render`, can drive it.
+//!
+//! The emulation is functional, not cycle accurate, but it estimates the
+//! cycles the code takes on the real processor. `budget` uses these to
+//! check that the callbacks fit in the time the firmware gives them.
+
+use std::fmt;
+
+use logue::platform::USER_API_VERSION;
+use logue::userosc::*;
+
+pub mod budget;
+pub mod cpu;
+pub mod firmware;
+pub mod memory;
+
+use cpu::Cpu;
+use firmware::Service;
+use memory::Memory;
+
+/// Where oscillators are loaded, and the space they may take, as in
+/// `userosc.x`.
+pub const OSC_ORIGIN: u32 = 0x2000_0000;
+pub const OSC_LENGTH: u32 = 32 * 1024;
+
+/// Offset of the first callback in the hook table.
+const HOOK_CALLBACKS_OFFSET: u32 = 16;
+
+/// The stack the callbacks run on, at the top of the SRAM the firmware
+/// keeps for itself.
+const STACK_TOP: u32 = 0x2002_0000;
+const STACK_SIZE: u32 = 8 * 1024;
+
+/// The firmware's copies of the parameters and output buffer passed to
+/// the callbacks.
+const PARAMS: u32 = 0x2001_0000;
+const BUFFER: u32 = PARAMS + 0x100;
+const MAX_FRAMES: u32 = 64;
+
+/// The return address given to callbacks. Nothing is mapped there, so
+/// reaching it can only mean the callback returned.
+const RETURN: u32 = 0xffff_fffe;
+
+/// Instructions a callback may execute before it is assumed to be stuck.
+const MAX_INSTRUCTIONS: u64 = 50_000_000;
+
+/// Why emulation stopped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// An access to an address nothing is mapped at.
+    Unmapped { addr: u32 },
+    /// A write to read-only memory, such as a firmware table.
+    ReadOnly { addr: u32 },
+    /// An instruction that is undefined, or that the interpreter does
+    /// not support.
+    Undefined { pc: u32, insn: u32 },
+    /// A branch that would switch to the ARM instruction set, which the
+    /// Cortex-M4 does not have.
+    ArmState { pc: u32, target: u32 },
+    /// A `BKPT` instruction.
+    Breakpoint { pc: u32 },
+    /// A callback that did not return within `MAX_INSTRUCTIONS`.
+    Timeout { pc: u32 },
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Fault::Unmapped { addr } => write!(f, "access to unmapped address {:#010x}", addr),
+            Fault::ReadOnly { addr } => write!(f, "write to read-only address {:#010x}", addr),
+            Fault::Undefined { pc, insn } => {
+                write!(f, "undefined or unsupported instruction {:#x} at {:#010x}", insn, pc)
+            }
+            Fault::ArmState { pc, target } => {
+                write!(f, "branch to ARM state at {:#010x} (target {:#010x})", pc, target)
+            }
+            Fault::Breakpoint { pc } => write!(f, "breakpoint at {:#010x}", pc),
+            Fault::Timeout { pc } => {
+                write!(f, "no return after {} instructions, at {:#010x}", MAX_INSTRUCTIONS, pc)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Fault {}
+
+/// Why a payload cannot be loaded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoadError {
+    /// The payload is larger than the space for oscillators.
+    TooLarge(usize),
+    /// The payload does not start with an oscillator hook table.
+    NotAnOscillator,
+    /// The hook table names a platform with no known firmware.
+    UnknownPlatform(u8),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::TooLarge(size) => {
+                write!(f, "payload of {} bytes does not fit in {} bytes", size, OSC_LENGTH)
+            }
+            LoadError::NotAnOscillator => write!(f, "payload has no oscillator hook table"),
+            LoadError::UnknownPlatform(p) => write!(f, "unknown platform {}", p),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// The callbacks of an oscillator hook table, in order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hook {
+    Init,
+    Cycle,
+    On,
+    Off,
+    Mute,
+    Value,
+    Param,
+}
+
+/// An oscillator payload loaded on an emulated Cortex-M4.
+pub struct Unit {
+    cpu: Cpu,
+    mem: Memory,
+    services: Vec<(u32, Service)>,
+}
+
+impl Unit {
+    /// Load `payload`, the contents of a unit file's `payload.bin`, with
+    /// the firmware of the platform its hook table names.
+    pub fn load(payload: &[u8]) -> Result<Unit, LoadError> {
+        if payload.len() > OSC_LENGTH as usize {
+            return Err(LoadError::TooLarge(payload.len()));
+        }
+        if payload.len() < 64 || payload[..4] != USER_OSC_MAGIC {
+            return Err(LoadError::NotAnOscillator);
+        }
+        let platform = payload[8];
+
+        let mut mem = Memory::new();
+        mem.map("oscillator", OSC_ORIGIN, OSC_LENGTH, true);
+        mem.load(OSC_ORIGIN, payload).unwrap();
+        mem.map("stack", STACK_TOP - STACK_SIZE, STACK_SIZE, true);
+        mem.map("callback arguments", PARAMS, BUFFER + 4 * MAX_FRAMES - PARAMS, true);
+        let services = firmware::map(&mut mem, (platform as u32) << 8, USER_API_VERSION)
+            .ok_or(LoadError::UnknownPlatform(platform))?;
+
+        Ok(Unit { cpu: Cpu::new(), mem, services })
+    }
+
+    /// Instructions executed by all calls so far.
+    pub fn instructions(&self) -> u64 {
+        self.cpu.instructions
+    }
+
+    /// Estimated cycles taken by all calls so far, including those spent
+    /// in the firmware functions they call.
+    pub fn cycles(&self) -> u64 {
+        self.cpu.cycles
+    }
+
+    /// The memory of the emulated system.
+    pub fn memory(&mut self) -> &mut Memory {
+        &mut self.mem
+    }
+
+    /// Call a callback with up to four word arguments, as the firmware
+    /// does, and run it until it returns.
+    pub fn call(&mut self, hook: Hook, args: &[u32]) -> Result<(), Fault> {
+        let entry = self.mem.read_u32(OSC_ORIGIN + HOOK_CALLBACKS_OFFSET + 4 * hook as u32)?;
+        for (i, &arg) in args.iter().enumerate() {
+            self.cpu.r[i] = arg;
+        }
+        self.cpu.r[13] = STACK_TOP;
+        self.cpu.r[14] = RETURN | 1;
+        if entry & 1 == 0 {
+            return Err(Fault::ArmState { pc: RETURN, target: entry });
+        }
+        self.cpu.r[15] = entry & !1;
+
+        let start = self.cpu.instructions;
+        while self.cpu.r[15] != RETURN {
+            let pc = self.cpu.r[15];
+            if let Some(&(_, service)) = self.services.iter().find(|s| s.0 == pc) {
+                let (value, float) = service.call();
+                self.cpu.cycles += service.cycles();
+                if float {
+                    self.cpu.s[0] = value;
+                } else {
+                    self.cpu.r[0] = value;
+                }
+                let lr = self.cpu.r[14];
+                if lr & 1 == 0 {
+                    return Err(Fault::ArmState { pc, target: lr });
+                }
+                self.cpu.r[15] = lr & !1;
+                continue;
+            }
+            if self.cpu.instructions - start > MAX_INSTRUCTIONS {
+                return Err(Fault::Timeout { pc });
+            }
+            self.cpu.step(&mut self.mem)?;
+        }
+        Ok(())
+    }
+
+    fn write_params(&mut self, params: &UserOscParams) -> u32 {
+        let mut bytes = Vec::with_capacity(16);
+        bytes.extend(params.shape_lfo.to_le_bytes());
+        for half in [params.pitch, params.cutoff, params.resonance] {
+            bytes.extend(half.to_le_bytes());
+        }
+        for half in params.reserved0 {
+            bytes.extend(half.to_le_bytes());
+        }
+        self.mem.write(PARAMS, &bytes).unwrap();
+        PARAMS
+    }
+
+    /// Call a callback for `Oscillator`, which has no way to report
+    /// faults: panic with where it happened.
+    fn call_or_panic(&mut self, hook: Hook, args: &[u32]) {
+        if let Err(fault) = self.call(hook, args) {
+            let place = match fault {
+                Fault::Unmapped { addr } | Fault::ReadOnly { addr } => self.mem.describe(addr),
+                _ => None,
+            };
+            let pc = self.cpu.pc();
+            match place {
+                Some(place) => panic!("{:?} callback: {}, {}, by the instruction at {:#010x}", hook, fault, place, pc),
+                None => panic!("{:?} callback: {}, by the instruction at {:#010x}", hook, fault, pc),
+            }
+        }
+    }
+}
+
+impl Oscillator for Unit {
+    fn init(&mut self, platform: u32, api: u32) {
+        self.call_or_panic(Hook::Init, &[platform, api]);
+    }
+
+    fn cycle(&mut self, params: &UserOscParams, yn: &mut [i32]) {
+        assert!(yn.len() <= MAX_FRAMES as usize, "cannot render {} frames in a cycle", yn.len());
+        let p = self.write_params(params);
+        let bytes: Vec<u8> = yn.iter().flat_map(|y| y.to_le_bytes()).collect();
+        self.mem.write(BUFFER, &bytes).unwrap();
+        self.call_or_panic(Hook::Cycle, &[p, BUFFER, yn.len() as u32]);
+        for (i, y) in yn.iter_mut().enumerate() {
+            *y = self.mem.read_u32(BUFFER + 4 * i as u32).unwrap() as i32;
+        }
+    }
+
+    fn note_on(&mut self, params: &UserOscParams) {
+        let p = self.write_params(params);
+        self.call_or_panic(Hook::On, &[p]);
+    }
+
+    fn note_off(&mut self, params: &UserOscParams) {
+        let p = self.write_params(params);
+        self.call_or_panic(Hook::Off, &[p]);
+    }
+
+    fn mute(&mut self, params: &UserOscParams) {
+        let p = self.write_params(params);
+        self.call_or_panic(Hook::Mute, &[p]);
+    }
+
+    fn value(&mut self, value: u16) {
+        self.call_or_panic(Hook::Value, &[value as u32]);
+    }
+
+    fn param(&mut self, index: UserOscParamId, value: u16) {
+        self.call_or_panic(Hook::Param, &[u16::from(index) as u32, value as u32]);
+    }
+}
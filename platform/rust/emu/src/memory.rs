@@ -0,0 +1,141 @@
+//! The address space seen by the emulated unit: a few regions of memory,
+//! little-endian like the Cortex-M4, with no caches or peripherals.
+
+use std::cell::Cell;
+
+use crate::Fault;
+
+/// A contiguous range of memory.
+struct Region {
+    name: &'static str,
+    base: u32,
+    data: Vec<u8>,
+    writable: bool,
+}
+
+impl Region {
+    fn contains(&self, addr: u32, len: u32) -> bool {
+        addr >= self.base && (addr - self.base) as u64 + len as u64 <= self.data.len() as u64
+    }
+}
+
+/// The memory of an emulated system. Accesses outside of every region,
+/// or writes to read-only ones, fault rather than being ignored.
+#[derive(Default)]
+pub struct Memory {
+    regions: Vec<Region>,
+    accesses: Cell<u64>,
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map `size` bytes of zeroed memory at `base`.
+    ///
+    /// Panics if the region overlaps one already mapped.
+    pub fn map(&mut self, name: &'static str, base: u32, size: u32, writable: bool) {
+        let end = base as u64 + size as u64;
+        for r in &self.regions {
+            let r_end = r.base as u64 + r.data.len() as u64;
+            assert!(end <= r.base as u64 || base as u64 >= r_end,
+                    "{} overlaps {} in the memory map", name, r.name);
+        }
+        self.regions.push(Region { name, base, data: vec![0; size as usize], writable });
+    }
+
+    /// The name of the region containing `addr`, if any.
+    pub fn region_name(&self, addr: u32) -> Option<&'static str> {
+        self.regions.iter().find(|r| r.contains(addr, 1)).map(|r| r.name)
+    }
+
+    /// Where `addr` is relative to the regions: in one, or just past the
+    /// end of one, as when an index into a table is off by one.
+    pub fn describe(&self, addr: u32) -> Option<String> {
+        if let Some(name) = self.region_name(addr) {
+            return Some(format!("in {}", name));
+        }
+        self.regions.iter()
+            .find(|r| (r.base as u64 + r.data.len() as u64..r.base as u64 + r.data.len() as u64 + 16)
+                .contains(&(addr as u64)))
+            .map(|r| format!("past the end of {}", r.name))
+    }
+
+    /// Reads and writes made so far, of any size, not counting `load`.
+    pub fn accesses(&self) -> u64 {
+        self.accesses.get()
+    }
+
+    fn region(&self, addr: u32, len: u32) -> Result<&Region, Fault> {
+        self.regions.iter()
+            .find(|r| r.contains(addr, len))
+            .ok_or(Fault::Unmapped { addr })
+    }
+
+    fn region_mut(&mut self, addr: u32, len: u32) -> Result<&mut Region, Fault> {
+        self.regions.iter_mut()
+            .find(|r| r.contains(addr, len))
+            .ok_or(Fault::Unmapped { addr })
+    }
+
+    /// Read `buf.len()` bytes at `addr`.
+    pub fn read(&self, addr: u32, buf: &mut [u8]) -> Result<(), Fault> {
+        self.accesses.set(self.accesses.get() + 1);
+        let r = self.region(addr, buf.len() as u32)?;
+        let start = (addr - r.base) as usize;
+        buf.copy_from_slice(&r.data[start..start + buf.len()]);
+        Ok(())
+    }
+
+    /// Write `bytes` at `addr`, which must be writable.
+    pub fn write(&mut self, addr: u32, bytes: &[u8]) -> Result<(), Fault> {
+        self.accesses.set(self.accesses.get() + 1);
+        let r = self.region_mut(addr, bytes.len() as u32)?;
+        if !r.writable {
+            return Err(Fault::ReadOnly { addr });
+        }
+        let start = (addr - r.base) as usize;
+        r.data[start..start + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Write `bytes` at `addr`, even in a read-only region, as when
+    /// loading an image.
+    pub fn load(&mut self, addr: u32, bytes: &[u8]) -> Result<(), Fault> {
+        let r = self.region_mut(addr, bytes.len() as u32)?;
+        let start = (addr - r.base) as usize;
+        r.data[start..start + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    pub fn read_u8(&self, addr: u32) -> Result<u8, Fault> {
+        let mut b = [0; 1];
+        self.read(addr, &mut b)?;
+        Ok(b[0])
+    }
+
+    pub fn read_u16(&self, addr: u32) -> Result<u16, Fault> {
+        let mut b = [0; 2];
+        self.read(addr, &mut b)?;
+        Ok(u16::from_le_bytes(b))
+    }
+
+    pub fn read_u32(&self, addr: u32) -> Result<u32, Fault> {
+        let mut b = [0; 4];
+        self.read(addr, &mut b)?;
+        Ok(u32::from_le_bytes(b))
+    }
+
+    pub fn write_u8(&mut self, addr: u32, value: u8) -> Result<(), Fault> {
+        self.write(addr, &[value])
+    }
+
+    pub fn write_u16(&mut self, addr: u32, value: u16) -> Result<(), Fault> {
+        self.write(addr, &value.to_le_bytes())
+    }
+
+    pub fn write_u32(&mut self, addr: u32, value: u32) -> Result<(), Fault> {
+        self.write(addr, &value.to_le_bytes())
+    }
+}
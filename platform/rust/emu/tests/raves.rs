@@ -0,0 +1,102 @@
+//! The shipped `raves` payload, run on the emulator, against `raves`
+//! built for the host.
+//!
+//! The unit file is built with `cargo xtask package`, exactly as for the
+//! synthesizers, in a target directory of its own so that the nested
+//! build does not wait on the one running the tests.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::OnceLock;
+
+use logue::host::osc_rand_seed;
+use logue::host::sim::*;
+use logue::platform::*;
+use logue::userprg::K_USER_MODULE_OSC;
+use logue_emu::budget::*;
+use logue_emu::{LoadError, Unit};
+use raves::manifest::MANIFEST;
+use raves::Raves;
+use zip::ZipArchive;
+
+/// The `payload.bin` of `raves.ntkdigunit`, built once for all tests.
+fn payload() -> &'static [u8] {
+    static PAYLOAD: OnceLock<Vec<u8>> = OnceLock::new();
+    PAYLOAD.get_or_init(|| {
+        let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("emu");
+        let status = Command::new(env!("CARGO"))
+            .args(["xtask", "package", "--platform", "nutekt-digital", "--out"])
+            .arg(&dir)
+            .arg("raves")
+            .env("CARGO_TARGET_DIR", dir.join("target"))
+            .status()
+            .unwrap();
+        assert!(status.success(), "packaging raves failed");
+
+        let mut archive = ZipArchive::new(File::open(dir.join("raves.ntkdigunit")).unwrap()).unwrap();
+        let mut payload = Vec::new();
+        archive.by_name("raves/payload.bin").unwrap().read_to_end(&mut payload).unwrap();
+        payload
+    })
+}
+
+fn simulator() -> Simulator {
+    Simulator::new(K_USER_TARGET_NUTEKTDIGITAL | K_USER_MODULE_OSC, USER_API_VERSION)
+}
+
+fn profile() -> Profile {
+    let mut profile = Profile::from_manifest(&MANIFEST);
+    profile.cycles = 300;
+    profile
+}
+
+#[test]
+fn matches_host_build() {
+    let profile = profile();
+    for seed in 1..=10 {
+        let schedule = Schedule::random(seed, &profile);
+
+        osc_rand_seed(seed);
+        let mut unit = Unit::load(payload()).unwrap();
+        let emulated = simulator().run(&mut unit, &schedule);
+
+        osc_rand_seed(seed);
+        let host = simulator().run(&mut Raves::new(), &schedule);
+
+        assert!(host.iter().any(|&y| y != 0), "silent render of the schedule of seed {}", seed);
+        if let Some(i) = (0..host.len()).find(|&i| emulated[i] != host[i]) {
+            panic!("sample {} of the schedule of seed {} differs: {} emulated, {} on the host",
+                   i, seed, emulated[i], host[i]);
+        }
+    }
+}
+
+#[test]
+fn rejects_other_payloads() {
+    let mut other = payload().to_vec();
+    other[..4].copy_from_slice(b"UMOD");
+    assert_eq!(Unit::load(&other).err(), Some(LoadError::NotAnOscillator));
+
+    let mut other = payload().to_vec();
+    other[8] = 9;
+    assert_eq!(Unit::load(&other).err(), Some(LoadError::UnknownPlatform(9)));
+}
+
+#[test]
+fn fits_cycle_budget() {
+    let mut meter = Meter::new(Unit::load(payload()).unwrap());
+    for seed in 1..=10 {
+        osc_rand_seed(seed);
+        simulator().run(&mut meter, &full_blocks(&Schedule::random(seed, &profile())));
+    }
+    let block = block_cycles(DEFAULT_CLOCK_HZ);
+    for hook in HOOKS {
+        let usage = meter.usage(hook);
+        assert!(usage.calls > 0, "{:?} was never called", hook);
+        let percent = 100.0 * usage.worst as f64 / block as f64;
+        assert!(!is_budgeted(hook) || percent <= DEFAULT_MAX_PERCENT,
+                "{:?} takes up to {} cycles, {:.2}% of the {} in a block", hook, usage.worst, percent, block);
+    }
+}
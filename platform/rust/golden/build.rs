@@ -0,0 +1,25 @@
+use std::path::Path;
+
+/// Compile the C++ demo units for the host, against the headers of the
+/// SDK and the stand-in for CMSIS in `cpp`. The symbols the firmware
+/// would provide come from the `host` feature of `logue`.
+fn main() {
+    let sdk = Path::new("../../nutekt-digital");
+
+    cc::Build::new()
+        .cpp(true)
+        .file("cpp/waves.cpp")
+        .include("cpp")
+        .include(sdk.join("inc"))
+        .include(sdk.join("inc/utils"))
+        .include(sdk.join("inc/dsp"))
+        .flag_if_supported("-std=c++11")
+        .flag_if_supported("-fno-exceptions")
+        .flag_if_supported("-fno-rtti")
+        .warnings(false)
+        .compile("waves");
+
+    println!("cargo:rerun-if-changed=cpp");
+    println!("cargo:rerun-if-changed={}", sdk.join("inc").display());
+    println!("cargo:rerun-if-changed={}", sdk.join("demos/waves").display());
+}
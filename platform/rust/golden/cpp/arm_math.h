@@ -0,0 +1,37 @@
+/*
+ * Host stand-in for the CMSIS `arm_math.h` included by `cortexm4.h`.
+ *
+ * Only the types and the intrinsics used by the inline helpers of the
+ * SDK headers are provided. The SIMD intrinsics are declared but not
+ * defined: the demo units do not use the helpers calling them, and
+ * linking fails if that changes.
+ */
+
+#ifndef __host_arm_math_h
+#define __host_arm_math_h
+
+#include <stdint.h>
+
+typedef int8_t  q7_t;
+typedef int16_t q15_t;
+typedef int32_t q31_t;
+typedef int64_t q63_t;
+typedef float   float32_t;
+
+#define __SIMD32_TYPE int32_t
+
+static inline int32_t __SSAT(int32_t x, uint32_t bits) {
+  const int32_t max = (1 << (bits - 1)) - 1;
+  return x < -max - 1 ? -max - 1 : x > max ? max : x;
+}
+
+static inline uint32_t __USAT(int32_t x, uint32_t bits) {
+  const int32_t max = (1 << bits) - 1;
+  return x < 0 ? 0 : x > max ? max : x;
+}
+
+int32_t __QSUB(int32_t a, int32_t b);
+int32_t __QSUB16(int32_t a, int32_t b);
+int32_t __SEL(int32_t a, int32_t b);
+
+#endif // __host_arm_math_h
@@ -0,0 +1,17 @@
+/*
+ * Host build of the `waves` demo oscillator.
+ *
+ * The unit keeps its state in a static instance, constructed when the
+ * firmware loads it. `waves_reset` constructs it again, so that each
+ * test starts from a freshly loaded unit.
+ */
+
+#include <new>
+
+#include "../../../nutekt-digital/demos/waves/waves.cpp"
+
+extern "C" void waves_reset(void)
+{
+  s_waves.~Waves();
+  new (&s_waves) Waves();
+}
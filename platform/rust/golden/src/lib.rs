@@ -0,0 +1,67 @@
+//! Host builds of the C++ demo units of the SDK, linked against the
+//! `host` stand-ins of `logue`, so that the Rust ports can be checked
+//! against the originals sample by sample.
+//!
+//! The C++ units keep their state in statics, so there is only one
+//! instance of each: creating one waits until any other is dropped.
+
+use std::sync::{Mutex, MutexGuard};
+
+use logue::userosc::*;
+
+extern "C" {
+    fn waves_reset();
+    fn _hook_init(platform: u32, api: u32);
+    fn _hook_cycle(params: &UserOscParams, yn: *mut i32, frames: u32);
+    fn _hook_on(params: &UserOscParams);
+    fn _hook_off(params: &UserOscParams);
+    fn _hook_param(index: u16, value: u16);
+}
+
+static WAVES: Mutex<()> = Mutex::new(());
+
+/// The `waves` demo oscillator (`demos/waves/waves.cpp`).
+pub struct Waves {
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Waves {
+    /// Take the unit and put it back in the state it is loaded in.
+    ///
+    /// Like loading the unit on the synthesizer, this draws from the
+    /// noise source: twice, since the constructor of `Waves` replaces
+    /// its initial `State` with a new one.
+    pub fn new() -> Self {
+        let lock = WAVES.lock().unwrap_or_else(|e| e.into_inner());
+        unsafe { waves_reset() };
+        Waves { _lock: lock }
+    }
+}
+
+impl Default for Waves {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Oscillator for Waves {
+    fn init(&mut self, platform: u32, api: u32) {
+        unsafe { _hook_init(platform, api) }
+    }
+
+    fn cycle(&mut self, params: &UserOscParams, yn: &mut [i32]) {
+        unsafe { _hook_cycle(params, yn.as_mut_ptr(), yn.len() as u32) }
+    }
+
+    fn note_on(&mut self, params: &UserOscParams) {
+        unsafe { _hook_on(params) }
+    }
+
+    fn note_off(&mut self, params: &UserOscParams) {
+        unsafe { _hook_off(params) }
+    }
+
+    fn param(&mut self, index: UserOscParamId, value: u16) {
+        unsafe { _hook_param(index.into(), value) }
+    }
+}
@@ -0,0 +1,128 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Platforms that can be selected with a cargo feature of the same name.
+const PLATFORMS: [&str; 3] = ["prologue", "minilogue-xd", "nutekt-digital"];
+
+/// Linker scripts generated for each kind of unit, along with the file
+/// listing the symbols the firmware exports to that kind of unit.
+const SCRIPTS: [(&str, &str); 4] = [
+    ("userosc.x", "osc_api.syms"),
+    ("usermodfx.x", "main_api.syms"),
+    ("userdelfx.x", "main_api.syms"),
+    ("userrevfx.x", "main_api.syms"),
+];
+
+fn feature_enabled(name: &str) -> bool {
+    let var = format!("CARGO_FEATURE_{}", name.to_uppercase().replace('-', "_"));
+    env::var_os(var).is_some()
+}
+
+/// Generate the unit linker scripts for the selected platform in
+/// `OUT_DIR` and add it to the search path, so units depending on this
+/// crate can simply pass e.g. `--script=userosc.x` to the linker.
+///
+/// Each script is the platform-independent memory layout followed by the
+/// addresses of the symbols exported by that platform's firmware.
+fn main() {
+    if feature_enabled("host") {
+        write_host_luts(&PathBuf::from(env::var_os("OUT_DIR").unwrap()));
+    }
+
+    let selected: Vec<&str> = PLATFORMS.iter().copied().filter(|p| feature_enabled(p)).collect();
+    println!("cargo:rerun-if-changed=build.rs");
+    if selected.len() != 1 {
+        // Reported with a proper message by `compile_error!` in lib.rs.
+        return;
+    }
+    let platform = selected[0];
+
+    let out = &PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    for (name, syms) in &SCRIPTS {
+        let sources = [format!("scripts/{}", name), format!("scripts/{}/{}", platform, syms)];
+        let mut script = File::create(out.join(name)).unwrap();
+        for path in &sources {
+            script.write_all(&fs::read(path).unwrap()).unwrap();
+            script.write_all(b"\n").unwrap();
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+    println!("cargo:rustc-link-search={}", out.display());
+}
+
+/// Write `host_luts.rs`, the tables the firmware provides, for the `host`
+/// feature. They are computed from their descriptions in `osc_api.h`
+/// rather than copied from the firmware, so values differ slightly, and
+/// the wave banks are stand-ins with the same layout (see `src/host.rs`).
+fn write_host_luts(out: &std::path::Path) {
+    use std::f64::consts::PI;
+    use std::fmt::Write as _;
+
+    fn table(src: &mut String, name: &str, ty: &str, values: impl Iterator<Item = f64>) {
+        writeln!(src, "pub static {}: {} = [", name, ty).unwrap();
+        for v in values {
+            writeln!(src, "    {:?},", v as f32).unwrap();
+        }
+        writeln!(src, "];").unwrap();
+    }
+
+    let mut src = String::new();
+
+    // Equal temperament with A4 (note 69) at 440 Hz, capped at
+    // k_note_max_hz, which note 138 reaches.
+    table(&mut src, "MIDI_TO_HZ_LUT_F", "[f32; K_MIDI_TO_HZ_SIZE]",
+          (0..152).map(|n| (440.0 * 2f64.powf((n as f64 - 69.0) / 12.0)).min(23679.643054)));
+
+    // Quantization scale for a bit depth falling exponentially from 24
+    // bits (scale 2^23) at 0.0 to 1 bit (scale 1) at 1.0.
+    table(&mut src, "BITRES_LUT_F", "[f32; K_BITRES_LUT_SIZE]",
+          (0..=128).map(|i| 2f64.powf(23.0 * (1.0 - i as f64 / 128.0))));
+
+    // tan(pi*x) for x in [0, 0.49].
+    table(&mut src, "TANPI_LUT_F", "[f32; K_TANPI_LUT_SIZE]",
+          (0..=256).map(|i| (PI * 0.49 * i as f64 / 256.0).tan()));
+
+    // Cubic saturation for x in [0, 1]: linear with the gain given in
+    // `osc_api.h` up to 1 - 1/sqrt(3), then a cubic that levels off at 1.
+    let (knee, gain) = (0.42264973081, 1.2383127573);
+    table(&mut src, "CUBICSAT_LUT_F", "[f32; K_CUBICSAT_LUT_SIZE]", (0..=128).map(|i| {
+        let x = i as f64 / 128.0;
+        gain * (x - (x - knee).max(0.0).powi(3))
+    }));
+
+    // Schetzen's soft clipping for x in [0, 1]: 2x up to 1/3, a
+    // parabola up to 2/3 and 1 beyond.
+    table(&mut src, "SCHETZEN_LUT_F", "[f32; K_SCHETZEN_LUT_SIZE]", (0..=128).map(|i| {
+        let x = i as f64 / 128.0;
+        if x < 1.0 / 3.0 { 2.0 * x } else if x < 2.0 / 3.0 { (3.0 - (2.0 - 3.0 * x).powi(2)) / 3.0 } else { 1.0 }
+    }));
+
+    // Band-limited single-cycle waves, with more harmonics from bank to
+    // bank and within each bank.
+    let banks = [("A", 16), ("B", 16), ("C", 14), ("D", 13), ("E", 15), ("F", 16)];
+    for (b, (bank, count)) in banks.iter().enumerate() {
+        writeln!(src, "pub static WAVES_{}_F: [WaveLUT; {}] = [", bank, count).unwrap();
+        for k in 0..*count {
+            let harmonics = ((1 << b) + k).min(63);
+            let odd_only = k % 2 == 1;
+            let wave: Vec<f64> = (0..128).map(|i| {
+                let phase = 2.0 * PI * i as f64 / 128.0;
+                (1..=harmonics)
+                    .filter(|h| !odd_only || h % 2 == 1)
+                    .map(|h| (h as f64 * phase).sin() / h as f64)
+                    .sum()
+            }).collect();
+            let peak = wave.iter().fold(0f64, |m, v| m.max(v.abs()));
+            write!(src, "    [").unwrap();
+            for v in wave.iter().chain(wave.first()) {
+                write!(src, "{:?}, ", (v / peak) as f32).unwrap();
+            }
+            writeln!(src, "],").unwrap();
+        }
+        writeln!(src, "];").unwrap();
+    }
+
+    fs::write(out.join("host_luts.rs"), src).unwrap();
+}
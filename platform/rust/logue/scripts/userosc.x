@@ -40,10 +40,12 @@
 /* Entry Point */
 ENTRY(_hook_init)
 
-/* Specify the memory areas */
+/* Specify the memory areas. `cargo xtask size` defines
+   _logue_size_report to lift the limit on SRAM, so that it can report
+   on units that do not fit and check them itself. */
 MEMORY
 {
-  SRAM   (rx) : org = 0x20000000, len = 32K
+  SRAM   (rx) : org = 0x20000000, len = DEFINED(_logue_size_report) ? 1M : 32K
 }
 
 /* ----------------------------------------------------------------------------- */
@@ -157,6 +159,17 @@ SECTIONS
     _eh_frame_end = .;
   } > SRAM
 
+  /* Oscillators have no SDRAM: fail to link if anything asks for it. */
+  .sdram (NOLOAD) : ALIGN(4) SUBALIGN(4)
+  {
+    _usr_sdram_start = .;
+    KEEP(*(.sdram*))
+    _usr_sdram_end = .;
+  } > SRAM
+
+  ASSERT(_usr_sdram_end == _usr_sdram_start,
+         "oscillators cannot place buffers in SDRAM")
+
   /*
   /DISCARD/
   {
@@ -168,32 +181,3 @@ SECTIONS
 
   /* .ARM.attributes 0 : { *(.ARM.attributes) } //*/
 }
-
-k_osc_api_version = 0x0800f000;
-k_osc_api_platform = 0x0800f004;
-midi_to_hz_lut_f = 0x0800f100;
-sqrtm2log_lut_f = 0x0800f360;
-tanpi_lut_f = 0x0800f764;
-log_lut_f = 0x0800fb68;
-bitres_lut_f = 0x0800ff6c;
-wt_par_lut_f = 0x08010170;
-wt_par_notes = 0x08010f8c;
-wt_sqr_lut_f = 0x08010f94;
-wt_sqr_notes = 0x08011db0;
-wt_saw_lut_f = 0x08011db8;
-wt_saw_notes = 0x08012bd4;
-wt_sine_lut_f = 0x08012bdc;
-schetzen_lut_f = 0x08012de0;
-cubicsat_lut_f = 0x08012fe4;
-wavesA = 0x080131e8;
-wavesB = 0x0801546c;
-wavesC = 0x080174ec;
-wavesD = 0x0801915c;
-wavesE = 0x0801abc4;
-wavesF = 0x0801ca3c;
-_osc_mcu_hash = 0x0801eabd;
-_osc_bl_saw_idx = 0x0801eac9;
-_osc_bl_sqr_idx = 0x0801ebb1;
-_osc_bl_par_idx = 0x0801ec99;
-_osc_rand = 0x0801ed81;
-_osc_white = 0x0801edb9;
@@ -0,0 +1,168 @@
+use crate::clamp_domain;
+use crate::mathutil::*;
+use crate::{bitres_lut_f, tanpi_lut_f};
+
+pub use crate::{K_BITRES_LUT_SIZE, K_BITRES_MASK, K_BITRES_SIZE, K_BITRES_SIZE_EXP};
+pub use crate::{K_TANPI_LUT_SIZE, K_TANPI_MASK, K_TANPI_RANGE_RECIP, K_TANPI_SIZE, K_TANPI_SIZE_EXP};
+
+extern "C" {
+    /// Current platform
+    pub static k_fx_api_platform: u32;
+
+    /// Current API version
+    pub static k_fx_api_version: u32;
+
+    fn _fx_mcu_hash() -> u32;
+    fn _fx_get_bpm() -> u16;
+    fn _fx_get_bpmf() -> f32;
+    fn _fx_rand() -> u32;
+    fn _fx_white() -> f32;
+
+    static wt_sine_lut_f: [f32; K_WT_SINE_LUT_SIZE];
+    static log_lut_f: [f32; K_LOG_LUT_SIZE];
+    static sqrtm2log_lut_f: [f32; K_SQRTM2LOG_LUT_SIZE];
+    static pow2_lut_f: [f32; K_POW2_LUT_SIZE];
+    static cubicsat_lut_f: [f32; K_CUBICSAT_LUT_SIZE];
+    static schetzen_lut_f: [f32; K_SCHETZEN_LUT_SIZE];
+}
+
+/// Get a MCU-specific "unique" hash.
+pub fn fx_mcu_hash() -> u32 {
+    unsafe { _fx_mcu_hash() }
+}
+
+/// Current tempo in beats per minute, multiplied by 10 to allow 1
+/// decimal of precision.
+pub fn fx_get_bpm() -> u16 {
+    unsafe { _fx_get_bpm() }
+}
+
+/// Current tempo in beats per minute.
+pub fn fx_get_bpmf() -> f32 {
+    unsafe { _fx_get_bpmf() }
+}
+
+/// Returns a random integer in [0, u32::MAX]. Generated with
+/// Park-Miller-Carta.
+pub fn fx_rand() -> u32 {
+    unsafe { _fx_rand() }
+}
+
+/// Gaussian white noise. Returns a value in [-1.0, 1.0].
+pub fn fx_white() -> f32 {
+    unsafe { _fx_white() }
+}
+
+// Sine half-wave. Wrap and negate for phase >= 0.5.
+pub const K_WT_SINE_SIZE_EXP: usize = 7;
+pub const K_WT_SINE_SIZE: usize = 1 << K_WT_SINE_SIZE_EXP;
+pub const K_WT_SINE_MASK: usize = K_WT_SINE_SIZE - 1;
+pub const K_WT_SINE_LUT_SIZE: usize = K_WT_SINE_SIZE + 1;
+
+/// Lookup value of sin(2*pi*x), where `x` is a phase ratio.
+pub fn fx_sinf(x: f32) -> f32 {
+    let p = x - (x as u32) as f32;
+
+    // half period stored -- wrap around and invert
+    let x0f = 2.0 * p * K_WT_SINE_SIZE as f32;
+    let x0p = x0f as usize;
+
+    let x0 = x0p & K_WT_SINE_MASK;
+    let x1 = (x0 + 1) & K_WT_SINE_MASK;
+
+    let lut = unsafe { &wt_sine_lut_f };
+    let y0 = linintf(x0f - x0p as f32, lut[x0], lut[x1]);
+    if x0p < K_WT_SINE_SIZE { y0 } else { -y0 }
+}
+
+/// Lookup value of cos(2*pi*x), where `x` is a phase ratio.
+pub fn fx_cosf(x: f32) -> f32 {
+    fx_sinf(x + 0.25)
+}
+
+pub const K_LOG_SIZE_EXP: usize = 8;
+pub const K_LOG_SIZE: usize = 1 << K_LOG_SIZE_EXP;
+pub const K_LOG_MASK: usize = K_LOG_SIZE - 1;
+pub const K_LOG_LUT_SIZE: usize = K_LOG_SIZE + 1;
+
+/// Lookup value of log(x) for `x` in [0.00001, 1.0].
+pub fn fx_logf(x: f32) -> f32 {
+    lut_linintf(unsafe { &log_lut_f }, x * K_LOG_SIZE as f32)
+}
+
+/// Lookup value of tan(pi*x) for `x` in [0.0001, 0.49].
+///
+/// As with `osc_tanpif`, the input is clamped to [0.0, 0.49], with NaN
+/// taken as 0.0.
+pub fn fx_tanpif(x: f32) -> f32 {
+    let idxf = clamp_domain(0.0, x, 0.49) * K_TANPI_RANGE_RECIP * K_TANPI_SIZE as f32;
+    lut_linintf(unsafe { &tanpi_lut_f }, idxf)
+}
+
+pub const K_SQRTM2LOG_SIZE_EXP: usize = 8;
+pub const K_SQRTM2LOG_SIZE: usize = 1 << K_SQRTM2LOG_SIZE_EXP;
+pub const K_SQRTM2LOG_MASK: usize = K_SQRTM2LOG_SIZE - 1;
+pub const K_SQRTM2LOG_BASE: f32 = 0.005;
+pub const K_SQRTM2LOG_RANGE_RECIP: f32 = 1.00502512562814; // 1/0.995
+pub const K_SQRTM2LOG_LUT_SIZE: usize = K_SQRTM2LOG_SIZE + 1;
+
+/// Lookup value of sqrt(-2*log(x)) for `x` in [0.005, 1.0].
+pub fn fx_sqrtm2logf(x: f32) -> f32 {
+    let idxf = (x - K_SQRTM2LOG_BASE) * K_SQRTM2LOG_RANGE_RECIP * K_SQRTM2LOG_SIZE as f32;
+    lut_linintf(unsafe { &sqrtm2log_lut_f }, idxf)
+}
+
+pub const K_POW2_SIZE_EXP: usize = 8;
+pub const K_POW2_SIZE: usize = 1 << K_POW2_SIZE_EXP;
+pub const K_POW2_SCALE: f32 = 85.3333333333333; // 256 / 3
+pub const K_POW2_MASK: usize = K_POW2_SIZE - 1;
+pub const K_POW2_LUT_SIZE: usize = K_POW2_SIZE + 1;
+
+/// Lookup value of 2^x for `x` in [0, 3.0].
+pub fn fx_pow2f(x: f32) -> f32 {
+    lut_linintf(unsafe { &pow2_lut_f }, x * K_POW2_SCALE)
+}
+
+/// Soft clip. `c` is a coefficient in [0, 1/3], and the result is in
+/// [-(1-c), (1-c)].
+pub fn fx_softclipf(c: f32, x: f32) -> f32 {
+    let x = clip1m1f(x);
+    x - c * (x*x*x)
+}
+
+pub const K_CUBICSAT_SIZE_EXP: usize = 7;
+pub const K_CUBICSAT_SIZE: usize = 1 << K_CUBICSAT_SIZE_EXP;
+pub const K_CUBICSAT_MASK: usize = K_CUBICSAT_SIZE - 1;
+pub const K_CUBICSAT_LUT_SIZE: usize = K_CUBICSAT_SIZE + 1;
+
+/// Cubic saturation of `x` in [-1.0, 1.0]. Cubic curve above
+/// 0.42264973081, gain: 1.2383127573.
+///
+/// Unlike `fx_sat_cubicf` in the C header, the table is indexed with the
+/// scaled input rather than `x` itself, which was always 0.
+pub fn fx_sat_cubicf(x: f32) -> f32 {
+    let xf = si_fabsf(clip1m1f(x)) * K_CUBICSAT_SIZE as f32;
+    si_copysignf(lut_linintf(unsafe { &cubicsat_lut_f }, xf), x)
+}
+
+pub const K_SCHETZEN_SIZE_EXP: usize = 7;
+pub const K_SCHETZEN_SIZE: usize = 1 << K_SCHETZEN_SIZE_EXP;
+pub const K_SCHETZEN_MASK: usize = K_SCHETZEN_SIZE - 1;
+pub const K_SCHETZEN_LUT_SIZE: usize = K_SCHETZEN_SIZE + 1;
+
+/// Schetzen saturation of `x` in [-1.0, 1.0].
+///
+/// As with `fx_sat_cubicf`, the table is indexed with the scaled input.
+pub fn fx_sat_schetzenf(x: f32) -> f32 {
+    let xf = si_fabsf(clip1m1f(x)) * K_SCHETZEN_SIZE as f32;
+    si_copysignf(lut_linintf(unsafe { &schetzen_lut_f }, xf), x)
+}
+
+/// Bit depth quantization scaling factor for `x` in [0, 1.0].
+/// Fractional bit depth, exponentially mapped, 1 to 24 bits.
+///
+/// As with `osc_bitresf`, the input is clamped to the domain, with NaN
+/// taken as 0.0.
+pub fn fx_bitresf(x: f32) -> f32 {
+    lut_linintf(unsafe { &bitres_lut_f }, clamp_domain(0.0, x, 1.0) * K_BITRES_SIZE as f32)
+}
@@ -0,0 +1,117 @@
+//! Software stand-ins for the symbols the firmware exports to
+//! oscillators, so that units can be built and tested on the host.
+//!
+//! The lookup tables are regenerated from their descriptions in
+//! `osc_api.h` by the build script, and the noise sources are seeded
+//! with `osc_rand_seed` to make runs reproducible. The firmware's wave
+//! banks are not public: the stand-ins have the same layout and are
+//! ordered by increasing harmonic content like the originals, but sound
+//! different.
+
+extern crate std;
+
+pub mod sim;
+
+use core::cell::Cell;
+
+use crate::fx_api::{K_CUBICSAT_LUT_SIZE, K_SCHETZEN_LUT_SIZE};
+use crate::wavebank::*;
+use crate::*;
+
+include!(concat!(env!("OUT_DIR"), "/host_luts.rs"));
+
+#[no_mangle]
+pub static midi_to_hz_lut_f: [f32; K_MIDI_TO_HZ_SIZE] = MIDI_TO_HZ_LUT_F;
+#[no_mangle]
+pub static bitres_lut_f: [f32; K_BITRES_LUT_SIZE] = BITRES_LUT_F;
+#[no_mangle]
+pub static tanpi_lut_f: [f32; K_TANPI_LUT_SIZE] = TANPI_LUT_F;
+#[no_mangle]
+pub static cubicsat_lut_f: [f32; K_CUBICSAT_LUT_SIZE] = CUBICSAT_LUT_F;
+#[no_mangle]
+pub static schetzen_lut_f: [f32; K_SCHETZEN_LUT_SIZE] = SCHETZEN_LUT_F;
+
+/// A wave bank as the firmware exports it: an array of pointers to
+/// tables.
+#[repr(transparent)]
+pub struct WaveBank<const N: usize>([*const WaveLUT; N]);
+
+// The tables pointed to are immutable statics.
+unsafe impl<const N: usize> Sync for WaveBank<N> {}
+
+macro_rules! wave_bank {
+    ($name:ident, $tables:ident, $n:expr) => {
+        #[no_mangle]
+        pub static $name: WaveBank<$n> = {
+            let mut bank = [core::ptr::null(); $n];
+            let mut i = 0;
+            while i < $n {
+                bank[i] = &$tables[i] as *const WaveLUT;
+                i += 1;
+            }
+            WaveBank(bank)
+        };
+    };
+}
+
+wave_bank!(wavesA, WAVES_A_F, K_WAVES_A_CNT);
+wave_bank!(wavesB, WAVES_B_F, K_WAVES_B_CNT);
+wave_bank!(wavesC, WAVES_C_F, K_WAVES_C_CNT);
+wave_bank!(wavesD, WAVES_D_F, K_WAVES_D_CNT);
+wave_bank!(wavesE, WAVES_E_F, K_WAVES_E_CNT);
+wave_bank!(wavesF, WAVES_F_F, K_WAVES_F_CNT);
+
+std::thread_local! {
+    /// Park-Miller-Carta generator state, in [1, 2^31-2]. Each thread
+    /// has its own, so that tests running in parallel are reproducible.
+    static RAND_STATE: Cell<u32> = const { Cell::new(1) };
+}
+
+/// Seed the generator behind `osc_rand` and `osc_white` for the current
+/// thread. A seed of 0, which would get the generator stuck, is replaced
+/// by 1.
+pub fn osc_rand_seed(seed: u32) {
+    RAND_STATE.with(|s| s.set(park_miller_seed(seed)));
+}
+
+/// The generator state for `seed`.
+fn park_miller_seed(seed: u32) -> u32 {
+    let seed = seed % 0x7FFF_FFFF;
+    if seed == 0 { 1 } else { seed }
+}
+
+/// One step of the Park-Miller minimal standard generator, computed
+/// without division as described by David Carta.
+fn park_miller_carta(seed: u32) -> u32 {
+    let lo = 16807 * (seed & 0xFFFF);
+    let hi = 16807 * (seed >> 16);
+    let lo = lo + ((hi & 0x7FFF) << 16) + (hi >> 15);
+    if lo > 0x7FFF_FFFF { lo - 0x7FFF_FFFF } else { lo }
+}
+
+/// Returns values in [1, 2^31-2].
+#[no_mangle]
+pub extern "C" fn _osc_rand() -> u32 {
+    RAND_STATE.with(|s| {
+        s.set(park_miller_carta(s.get()));
+        s.get()
+    })
+}
+
+/// Gaussian noise from the Box-Muller transform, with the radius
+/// limited to that of `sqrt(-2*log(0.005))` so the result stays in
+/// [-1.0, 1.0].
+#[no_mangle]
+pub extern "C" fn _osc_white() -> f32 {
+    const MAX_RADIUS: f32 = 3.255_247;
+    let u0 = _osc_rand() as f32 / 0x7FFF_FFFF as f32;
+    let u1 = _osc_rand() as f32 / 0x7FFF_FFFF as f32;
+    let r = (-2.0 * u0.max(0.005).ln()).sqrt();
+    r * (2.0 * core::f32::consts::PI * u1).cos() / MAX_RADIUS
+}
+
+/// A fixed stand-in for the MCU's hash.
+#[no_mangle]
+pub extern "C" fn _osc_mcu_hash() -> u32 {
+    0x4C4F_4755
+}
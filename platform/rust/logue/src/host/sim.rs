@@ -0,0 +1,233 @@
+//! A deterministic model of how the firmware calls an oscillator.
+//!
+//! The firmware calls `init` once when the unit is loaded, and from then
+//! on interleaves `cycle` calls, of up to 64 frames each, with the other
+//! callbacks as notes are played and parameters edited. Several
+//! parameter changes, note ons and offs can arrive between two cycles,
+//! and the shape LFO and pitch in the `UserOscParams` passed to the
+//! callbacks change from one call to the next. A `Schedule` is a
+//! sequence of such calls, written by hand or generated at random from a
+//! seed, and a `Simulator` plays it to a unit.
+
+extern crate std;
+
+use std::convert::TryFrom;
+use std::vec::Vec;
+
+use crate::manifest::Manifest;
+use crate::userosc::*;
+
+use super::{park_miller_carta, park_miller_seed};
+
+/// Largest number of frames the firmware renders in one cycle.
+pub const MAX_FRAMES: usize = 64;
+
+/// One step of a schedule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// Call `init`.
+    Init,
+    /// Call `param` with a raw parameter index and value. Unknown
+    /// indices are dropped, as by the callback `declare_oscillator!`
+    /// generates.
+    Param(u16, u16),
+    /// Set the pitch, then call `note_on`.
+    NoteOn(u16),
+    /// Call `note_off`.
+    NoteOff,
+    /// Call `mute`.
+    Mute,
+    /// Call `value`.
+    Value(u16),
+    /// Change the pitch passed to later calls, as pitch bend does.
+    Pitch(u16),
+    /// Change the shape LFO value passed to later calls.
+    ShapeLfo(i32),
+    /// Call `cycle` to render this many frames, from 1 to `MAX_FRAMES`.
+    Cycle(usize),
+}
+
+/// The values `Schedule::random` picks parameter changes from.
+#[derive(Clone, Debug)]
+pub struct Profile {
+    /// Largest value of each parameter, indexed like `UserOscParamId`.
+    pub param_max: [u16; 8],
+    /// Number of cycles to render.
+    pub cycles: usize,
+}
+
+impl Profile {
+    /// The parameter ranges of a unit's manifest: from 0 to the span of
+    /// each edit parameter, and 10-bit values for shape and shift-shape.
+    pub fn from_manifest(manifest: &Manifest) -> Self {
+        let mut param_max = [0; 8];
+        for (max, p) in param_max.iter_mut().zip(manifest.params) {
+            *max = (p.max - p.min) as u16;
+        }
+        param_max[6] = 1023;
+        param_max[7] = 1023;
+        Profile { param_max, cycles: 1000 }
+    }
+}
+
+/// A sequence of steps.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Schedule(pub Vec<Step>);
+
+impl Schedule {
+    /// Generate a schedule from `seed`, which always gives the same
+    /// schedule for the same profile.
+    ///
+    /// The unit is initialized and sent a value for every parameter, as
+    /// when it is loaded, then `profile.cycles` cycles are rendered. Most
+    /// are of the full 64 frames, and between them come bursts of
+    /// parameter changes, notes, mutes, and changes of pitch and LFO.
+    /// Changes often arrive while those from the previous burst are
+    /// still waiting for a cycle.
+    pub fn random(seed: u32, profile: &Profile) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut steps = Vec::new();
+
+        steps.push(Step::Init);
+        for (index, &max) in profile.param_max.iter().enumerate() {
+            steps.push(Step::Param(index as u16, rng.below(max as u32 + 1) as u16));
+        }
+
+        let mut pitch = 60 << 8;
+        let mut lfo = 0i32;
+        for _ in 0..profile.cycles {
+            if rng.chance(4) {
+                for _ in 0..=rng.below(4) {
+                    let index = rng.below(8) as usize;
+                    let value = rng.below(profile.param_max[index] as u32 + 1) as u16;
+                    steps.push(Step::Param(index as u16, value));
+                }
+            }
+            if rng.chance(16) {
+                pitch = rng.below(128 << 8) as u16;
+                steps.push(Step::NoteOn(pitch));
+            }
+            if rng.chance(32) {
+                steps.push(Step::NoteOff);
+            }
+            if rng.chance(64) {
+                steps.push(Step::Mute);
+            }
+            if rng.chance(64) {
+                steps.push(Step::Value(rng.below(1024) as u16));
+            }
+            if rng.chance(8) {
+                pitch = (pitch as i32 + rng.below(513) as i32 - 256).clamp(0, 0x7FFF) as u16;
+                steps.push(Step::Pitch(pitch));
+            }
+            if rng.chance(2) {
+                lfo = lfo.saturating_add((rng.next() as i32).wrapping_sub(1 << 30) >> 3);
+                steps.push(Step::ShapeLfo(lfo));
+            }
+            let frames = if rng.chance(4) { 1 + rng.below(MAX_FRAMES as u32) as usize } else { MAX_FRAMES };
+            steps.push(Step::Cycle(frames));
+        }
+
+        Schedule(steps)
+    }
+}
+
+/// Plays schedules to a unit, keeping track of the parameters the
+/// firmware passes to its callbacks.
+pub struct Simulator {
+    platform: u32,
+    api: u32,
+    params: UserOscParams,
+    buf: [i32; MAX_FRAMES],
+}
+
+impl Simulator {
+    /// A simulator for a runtime of the given platform and API version,
+    /// which `Step::Init` passes to the unit. Notes start at middle C.
+    pub fn new(platform: u32, api: u32) -> Self {
+        Simulator {
+            platform,
+            api,
+            params: UserOscParams {
+                shape_lfo: 0,
+                pitch: 60 << 8,
+                cutoff: 0x1fff,
+                resonance: 0,
+                reserved0: [0; 3],
+            },
+            buf: [0; MAX_FRAMES],
+        }
+    }
+
+    /// The parameters passed to the next callback.
+    pub fn params(&self) -> &UserOscParams {
+        &self.params
+    }
+
+    /// Take one step, returning the frames rendered by a cycle, or no
+    /// frames for other steps.
+    ///
+    /// Panics if a cycle asks for no frames or more than `MAX_FRAMES`.
+    pub fn step(&mut self, osc: &mut dyn Oscillator, step: Step) -> &[i32] {
+        match step {
+            Step::Init => osc.init(self.platform, self.api),
+            Step::Param(index, value) => {
+                if let Ok(id) = UserOscParamId::try_from(index) {
+                    osc.param(id, value);
+                }
+            }
+            Step::NoteOn(pitch) => {
+                self.params.pitch = pitch;
+                osc.note_on(&self.params);
+            }
+            Step::NoteOff => osc.note_off(&self.params),
+            Step::Mute => osc.mute(&self.params),
+            Step::Value(value) => osc.value(value),
+            Step::Pitch(pitch) => self.params.pitch = pitch,
+            Step::ShapeLfo(lfo) => self.params.shape_lfo = lfo,
+            Step::Cycle(frames) => {
+                assert!((1..=MAX_FRAMES).contains(&frames), "cannot render {} frames in a cycle", frames);
+                let out = &mut self.buf[..frames];
+                out.fill(0);
+                osc.cycle(&self.params, out);
+                return out;
+            }
+        }
+        &[]
+    }
+
+    /// Play a whole schedule, returning everything rendered.
+    pub fn run(&mut self, osc: &mut dyn Oscillator, schedule: &Schedule) -> Vec<i32> {
+        let mut out = Vec::new();
+        for &step in &schedule.0 {
+            out.extend_from_slice(self.step(osc, step));
+        }
+        out
+    }
+}
+
+/// A generator for schedules, separate from the noise sources so that
+/// generating a schedule does not change what the unit plays.
+struct Rng(u32);
+
+impl Rng {
+    fn new(seed: u32) -> Self {
+        Rng(park_miller_seed(seed))
+    }
+
+    /// Returns values in [1, 2^31-2].
+    fn next(&mut self) -> u32 {
+        self.0 = park_miller_carta(self.0);
+        self.0
+    }
+
+    /// Returns values in [0, n).
+    fn below(&mut self, n: u32) -> u32 {
+        ((self.next() as u64 * n as u64) >> 31) as u32
+    }
+
+    /// True once in `n` times on average.
+    fn chance(&mut self, n: u32) -> bool {
+        self.below(n) == 0
+    }
+}
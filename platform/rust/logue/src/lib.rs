@@ -0,0 +1,112 @@
+//! Rust bindings to the runtime that KORG logue synthesizers (prologue,
+//! minilogue xd and Nu:Tekt NTS-1) provide to user units.
+//!
+//! This covers the same ground as the C headers under `platform/*/inc`:
+//! the hook table layouts, the exported lookup tables and wave banks, and
+//! the small inline helpers built on top of them.
+//!
+//! Exactly one of the `prologue`, `minilogue-xd` or `nutekt-digital`
+//! features must be enabled to select the target platform. The
+//! platforms share the same runtime, but units are tagged with the
+//! platform they were built for and are linked against its firmware.
+//! On the host, the features may also be left out altogether, so that
+//! build scripts can use the platform-independent parts of the crate,
+//! such as `manifest`.
+//!
+//! The `host` feature provides stand-ins for the symbols the firmware
+//! exports to oscillators (see `host`), so that their DSP code can run
+//! in tests on the host.
+
+#![no_std]
+// Constants are transcribed digit-for-digit from the C headers.
+#![allow(clippy::excessive_precision)]
+
+#[cfg(all(target_os = "none",
+          not(any(feature = "prologue", feature = "minilogue-xd", feature = "nutekt-digital"))))]
+compile_error!("select a target platform with the `prologue`, `minilogue-xd` or `nutekt-digital` feature");
+
+#[cfg(any(all(feature = "prologue", feature = "minilogue-xd"),
+          all(feature = "prologue", feature = "nutekt-digital"),
+          all(feature = "minilogue-xd", feature = "nutekt-digital")))]
+compile_error!("only one target platform feature can be enabled at a time");
+
+#[cfg(all(target_os = "none", feature = "host"))]
+compile_error!("the `host` feature cannot be used on the synthesizers, which provide the real runtime");
+
+pub mod clipsat;
+pub mod delfx;
+pub mod fx_api;
+#[cfg(feature = "host")]
+pub mod host;
+pub mod manifest;
+pub mod mathutil;
+pub mod modfx;
+pub mod platform;
+pub mod random;
+pub mod revfx;
+pub mod sdram;
+pub mod userosc;
+pub mod userprg;
+pub mod wavebank;
+
+use mathutil::*;
+use platform::*;
+
+pub const K_MIDI_TO_HZ_SIZE: usize = 152;
+pub const K_NOTE_MOD_FSCALE: f32 = 0.00392156862745098f32;
+pub const K_NOTE_MAX_HZ: f32 = 23679.643054f32;
+
+pub const K_BITRES_SIZE_EXP : usize = 7;
+pub const K_BITRES_SIZE : usize = 1 << K_BITRES_SIZE_EXP;
+pub const K_BITRES_MASK : usize = K_BITRES_SIZE - 1;
+pub const K_BITRES_LUT_SIZE : usize = K_BITRES_SIZE + 1;
+
+pub const K_TANPI_SIZE_EXP: usize = 8;
+pub const K_TANPI_SIZE: usize = 1 << K_TANPI_SIZE_EXP;
+pub const K_TANPI_MASK: usize = K_TANPI_SIZE - 1;
+pub const K_TANPI_RANGE_RECIP: f32 = 2.04081632653061; // 1/0.49
+pub const K_TANPI_LUT_SIZE: usize = K_TANPI_SIZE + 1;
+
+extern "C" {
+    static midi_to_hz_lut_f: [f32; K_MIDI_TO_HZ_SIZE];
+    pub(crate) static bitres_lut_f: [f32; K_BITRES_LUT_SIZE];
+    pub(crate) static tanpi_lut_f: [f32; K_TANPI_LUT_SIZE];
+}
+
+/// `x` clamped to [`lo`, `hi`], with NaN taken as `lo`, to keep table
+/// lookups in bounds whatever the input.
+pub(crate) fn clamp_domain(lo: f32, x: f32, hi: f32) -> f32 {
+    if x >= hi { hi } else if x >= lo { x } else { lo }
+}
+
+/// Quantization scaling factor for a bit depth `x` in [0.0, 1.0],
+/// exponentially mapped from 24 bits at 0.0 to 1 bit at 1.0.
+///
+/// Unlike the C header, which reads past the end of the table at 1.0,
+/// the input is clamped to the domain, with NaN taken as 0.0.
+pub fn osc_bitresf(x: f32) -> f32 {
+    lut_linintf(unsafe { &bitres_lut_f }, clamp_domain(0.0, x, 1.0) * K_BITRES_SIZE as f32)
+}
+
+/// Lookup value of tan(pi*x) for `x` in [0.0001, 0.49].
+///
+/// The input is clamped to [0.0, 0.49], with NaN taken as 0.0, where the
+/// C header leaves bounding it to the caller.
+pub fn osc_tanpif(x: f32) -> f32 {
+    let idxf = clamp_domain(0.0, x, 0.49) * K_TANPI_RANGE_RECIP * K_TANPI_SIZE as f32;
+    lut_linintf(unsafe { &tanpi_lut_f }, idxf)
+}
+
+pub fn osc_w0f_for_note(note: u8, modulation: u8) -> f32{
+    let f0 = osc_notehzf(note);
+    let f1 = osc_notehzf(note + 1);
+    let f = clipmaxf(linintf(modulation as f32 * K_NOTE_MOD_FSCALE, f0, f1), K_NOTE_MAX_HZ);
+    f * K_SAMPLERATE_RECIP
+}
+
+/// Get Hertz value for `note`, which should be in the range [0-151].
+/// Larger values will be clipped to 151.
+pub fn osc_notehzf(note: u8) -> f32 {
+    let idx = clipmaxnote(note, K_MIDI_TO_HZ_SIZE - 1);
+    unsafe { *midi_to_hz_lut_f.get_unchecked(idx) }
+}
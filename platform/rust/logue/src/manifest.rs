@@ -0,0 +1,231 @@
+//! Unit manifests. Every unit is packaged with a `manifest.json`
+//! describing it and the parameters shown in the synthesizer's edit
+//! menu. Declaring these once in Rust lets a unit's build script check
+//! them against the limits of `userprg.h`, and the manifest be written
+//! for each platform when the unit is packaged, rather than keeping a
+//! hand-written file in sync with the code.
+
+use core::fmt;
+
+use crate::platform::*;
+use crate::userprg::*;
+
+/// An edit menu parameter: its name, range and unit. The unit is `"%"`
+/// for percentages or `""` for typeless values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Param {
+    pub name: &'static str,
+    pub min: i32,
+    pub max: i32,
+    pub unit: &'static str,
+}
+
+impl Param {
+    pub const fn new(name: &'static str, min: i32, max: i32, unit: &'static str) -> Self {
+        Param { name, min, max, unit }
+    }
+}
+
+/// The contents of a unit's `manifest.json`, apart from the platform,
+/// which is chosen when the unit is packaged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Manifest {
+    /// One of the `K_USER_MODULE_*` constants, other than
+    /// `K_USER_MODULE_GLOBAL`.
+    pub module: u32,
+    pub dev_id: u32,
+    pub prg_id: u32,
+    /// Version of the unit itself, encoded like an API version.
+    pub version: ApiVersion,
+    pub name: &'static str,
+    pub params: &'static [Param],
+}
+
+/// A reason a manifest would be rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ManifestError {
+    UnknownModule(u32),
+    NameTooLong(&'static str),
+    TooManyParams(usize),
+    ParamNameTooLong(&'static str),
+    ParamOutOfRange(&'static str),
+    ParamUnknownUnit(&'static str),
+    /// A name that is not printable ASCII or contains `"` or `\`.
+    BadName(&'static str),
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ManifestError::UnknownModule(m) =>
+                write!(f, "unknown module {}", m),
+            ManifestError::NameTooLong(n) =>
+                write!(f, "name \"{}\" is longer than {} characters", n, USER_PRG_NAME_LEN),
+            ManifestError::TooManyParams(n) =>
+                write!(f, "{} parameters declared, at most {} are allowed", n, USER_PRG_MAX_PARAM_COUNT),
+            ManifestError::ParamNameTooLong(n) =>
+                write!(f, "parameter name \"{}\" is longer than {} characters", n, USER_PRG_PARAM_NAME_LEN),
+            ManifestError::ParamOutOfRange(n) =>
+                write!(f, "range of parameter \"{}\" is empty or outside {}..{}",
+                       n, USER_PRG_PARAM_MIN_LIMIT, USER_PRG_PARAM_MAX_LIMIT),
+            ManifestError::ParamUnknownUnit(n) =>
+                write!(f, "parameter \"{}\" has a unit other than \"%\" or \"\"", n),
+            ManifestError::BadName(n) =>
+                write!(f, "\"{}\" is not printable ASCII without quotes or backslashes", n),
+        }
+    }
+}
+
+/// Name of a module as it appears in manifests.
+pub fn module_name(module: u32) -> Option<&'static str> {
+    match module {
+        K_USER_MODULE_MODFX => Some("modfx"),
+        K_USER_MODULE_DELFX => Some("delfx"),
+        K_USER_MODULE_REVFX => Some("revfx"),
+        K_USER_MODULE_OSC   => Some("osc"),
+        _ => None,
+    }
+}
+
+fn check_name(name: &'static str) -> Result<(), ManifestError> {
+    if name.bytes().all(|c| (b' '..=b'~').contains(&c) && c != b'"' && c != b'\\') {
+        Ok(())
+    } else {
+        Err(ManifestError::BadName(name))
+    }
+}
+
+impl Manifest {
+    /// Check the manifest against the limits the firmware imposes.
+    pub fn validate(&self) -> Result<(), ManifestError> {
+        module_name(self.module).ok_or(ManifestError::UnknownModule(self.module))?;
+        check_name(self.name)?;
+        if self.name.len() > USER_PRG_NAME_LEN {
+            return Err(ManifestError::NameTooLong(self.name));
+        }
+        if self.params.len() > USER_PRG_MAX_PARAM_COUNT {
+            return Err(ManifestError::TooManyParams(self.params.len()));
+        }
+        for p in self.params {
+            check_name(p.name)?;
+            if p.name.len() > USER_PRG_PARAM_NAME_LEN {
+                return Err(ManifestError::ParamNameTooLong(p.name));
+            }
+            if p.min < USER_PRG_PARAM_MIN_LIMIT || p.max > USER_PRG_PARAM_MAX_LIMIT || p.min > p.max {
+                return Err(ManifestError::ParamOutOfRange(p.name));
+            }
+            if !matches!(p.unit, "%" | "") {
+                return Err(ManifestError::ParamUnknownUnit(p.name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the manifest as JSON, for the platform with the given name
+    /// (as in `USER_TARGET_PLATFORM_NAME`). The manifest should be
+    /// validated first.
+    pub fn write_json<W: fmt::Write>(&self, platform: &str, w: &mut W) -> fmt::Result {
+        let api = ApiVersion(USER_API_VERSION);
+        let v = self.version;
+        writeln!(w, "{{")?;
+        writeln!(w, "    \"header\" : ")?;
+        writeln!(w, "    {{")?;
+        writeln!(w, "        \"platform\" : \"{}\",", platform)?;
+        writeln!(w, "        \"module\" : \"{}\",", module_name(self.module).unwrap_or(""))?;
+        writeln!(w, "        \"api\" : \"{}.{}-{}\",", api.major(), api.minor(), api.patch())?;
+        writeln!(w, "        \"dev_id\" : {},", self.dev_id)?;
+        writeln!(w, "        \"prg_id\" : {},", self.prg_id)?;
+        writeln!(w, "        \"version\" : \"{}.{}-{}\",", v.major(), v.minor(), v.patch())?;
+        writeln!(w, "        \"name\" : \"{}\",", self.name)?;
+        writeln!(w, "        \"num_param\" : {},", self.params.len())?;
+        write!(w, "        \"params\" : [")?;
+        for (i, p) in self.params.iter().enumerate() {
+            let sep = if i + 1 < self.params.len() { "," } else { "" };
+            // Align the columns as in hand-written manifests.
+            let pad = (USER_PRG_PARAM_NAME_LEN - 3).saturating_sub(p.name.len());
+            let upad = 2usize.saturating_sub(p.unit.len());
+            write!(w, "\n            [\"{}\",{:pad$} {:>3}, {:>3},{:upad$}\"{}\"]{}",
+                   p.name, "", p.min, p.max, "", p.unit, sep, pad = pad, upad = upad)?;
+        }
+        writeln!(w, "\n          ]")?;
+        writeln!(w, "    }}")?;
+        writeln!(w, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::string::String;
+    use std::vec;
+
+    use super::*;
+
+    /// The manifest of the C++ SDK's waves demo.
+    const WAVES: Manifest = Manifest {
+        module: K_USER_MODULE_OSC,
+        dev_id: 0,
+        prg_id: 0,
+        version: ApiVersion::new(1, 0, 1),
+        name: "waves",
+        params: &[
+            Param::new("Wave A",    0, 45,  ""),
+            Param::new("Wave B",    0, 43,  ""),
+            Param::new("Sub Wave",  0, 15,  ""),
+            Param::new("Sub Mix",   0, 100, "%"),
+            Param::new("Ring Mix",  0, 100, "%"),
+            Param::new("Bit Crush", 0, 100, "%"),
+        ],
+    };
+
+    #[test]
+    fn json_as_hand_written() {
+        let mut json = String::new();
+        WAVES.write_json("nutekt-digital", &mut json).unwrap();
+        assert_eq!(json, include_str!("../../../nutekt-digital/demos/waves/manifest.json"));
+    }
+
+    #[test]
+    fn json_for_platform() {
+        let mut json = String::new();
+        WAVES.write_json("prologue", &mut json).unwrap();
+        assert!(json.contains("\"platform\" : \"prologue\",\n"));
+        let empty = Manifest { params: &[], ..WAVES };
+        json.clear();
+        empty.write_json("prologue", &mut json).unwrap();
+        assert!(json.contains("\"num_param\" : 0,\n        \"params\" : [\n          ]\n"));
+    }
+
+    #[test]
+    fn valid() {
+        assert_eq!(WAVES.validate(), Ok(()));
+        const LIMITS: &[Param] = &[
+            Param::new("Twelve chars", USER_PRG_PARAM_MIN_LIMIT, USER_PRG_PARAM_MAX_LIMIT, "%"),
+            Param::new("", 0, 0, ""),
+        ];
+        let edge = Manifest { name: "Thirteen char", params: LIMITS, ..WAVES };
+        assert_eq!(edge.validate(), Ok(()));
+    }
+
+    #[test]
+    fn invalid() {
+        let with = |params: vec::Vec<Param>| Manifest { params: params.leak(), ..WAVES };
+        assert_eq!(Manifest { module: K_USER_MODULE_GLOBAL, ..WAVES }.validate(),
+                   Err(ManifestError::UnknownModule(K_USER_MODULE_GLOBAL)));
+        assert_eq!(Manifest { name: "Fourteen chars", ..WAVES }.validate(),
+                   Err(ManifestError::NameTooLong("Fourteen chars")));
+        assert_eq!(Manifest { name: "wa\"ves", ..WAVES }.validate(), Err(ManifestError::BadName("wa\"ves")));
+        assert_eq!(Manifest { name: "wavés", ..WAVES }.validate(), Err(ManifestError::BadName("wavés")));
+        assert_eq!(with(vec![Param::new("p", 0, 1, ""); 7]).validate(), Err(ManifestError::TooManyParams(7)));
+        assert_eq!(with(vec![Param::new("Thirteen char", 0, 1, "")]).validate(),
+                   Err(ManifestError::ParamNameTooLong("Thirteen char")));
+        assert_eq!(with(vec![Param::new("back\\slash", 0, 1, "")]).validate(),
+                   Err(ManifestError::BadName("back\\slash")));
+        for (min, max) in [(-101, 0), (0, 101), (1, 0)] {
+            assert_eq!(with(vec![Param::new("p", min, max, "")]).validate(), Err(ManifestError::ParamOutOfRange("p")),
+                       "range {}..{}", min, max);
+        }
+        assert_eq!(with(vec![Param::new("p", 0, 1, "Hz")]).validate(), Err(ManifestError::ParamUnknownUnit("p")));
+    }
+}
@@ -2,11 +2,11 @@ const F32_TO_Q31_C : u32 = 0x7FFFFFFF; // 2^31
 const Q31_TO_F32_C : f32 = 4.65661287307739e-010f32; // 1 / 2^31
 
 pub fn clip01f(x: f32) -> f32 {
-    if x > 1.0 { 1.0 } else if x < 0.0 { 0.0 } else { x }
+    x.clamp(0.0, 1.0)
 }
 
 pub fn clip1m1f(x: f32) -> f32 {
-    if x > 1.0 { 1.0 } else if x < -1.0 { -1.0 } else { x }
+    x.clamp(-1.0, 1.0)
 }
 
 pub fn clipminmaxf(lo: f32, x: f32, hi: f32) -> f32 {
@@ -30,6 +30,24 @@ pub fn linintf(fr: f32, x0: f32, x1: f32) -> f32 {
     x0 + fr * (x1 - x0)
 }
 
+/// Linear interpolation in `lut` at fractional index `idxf`. The index
+/// is clamped to the table, so out-of-range inputs extrapolate from the
+/// first or last segment instead of reading past either end.
+pub fn lut_linintf(lut: &[f32], idxf: f32) -> f32 {
+    let idx = (idxf as usize).min(lut.len() - 2);
+    linintf(idxf - idx as f32, lut[idx], lut[idx + 1])
+}
+
+/// Absolute value, by clearing the sign bit.
+pub fn si_fabsf(x: f32) -> f32 {
+    f32::from_bits(x.to_bits() & 0x7fff_ffff)
+}
+
+/// The magnitude of `x` with the sign of `y`.
+pub fn si_copysignf(x: f32, y: f32) -> f32 {
+    f32::from_bits((x.to_bits() & 0x7fff_ffff) | (y.to_bits() & 0x8000_0000))
+}
+
 pub fn q31_to_f32(x: i32) -> f32 {
     x as f32 * Q31_TO_F32_C
 }
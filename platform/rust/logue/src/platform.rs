@@ -0,0 +1,81 @@
+extern "C" {
+    /// Current platform
+    pub static k_osc_api_platform: u32;
+
+    /// Current API version
+    pub static k_osc_api_version: u32;
+
+    /// Get a MCU-specific "unique" hash.
+    fn _osc_mcu_hash() -> u32;
+
+}
+
+#[cfg(not(feature = "host"))]
+extern "C" {
+    /// Bounds of the `.bss` section, defined by the linker scripts.
+    static mut _bss_start: u8;
+    static mut _bss_end: u8;
+}
+
+pub const K_USER_TARGET_PROLOGUE: u32      = 1<<8;
+pub const K_USER_TARGET_MINILOGUEXD: u32   = 2<<8;
+pub const K_USER_TARGET_NUTEKTDIGITAL: u32 = 3<<8;
+
+/// The platform selected with the `prologue`, `minilogue-xd` or
+/// `nutekt-digital` feature.
+#[cfg(feature = "prologue")]
+pub const USER_TARGET_PLATFORM: u32 = K_USER_TARGET_PROLOGUE;
+#[cfg(feature = "minilogue-xd")]
+pub const USER_TARGET_PLATFORM: u32 = K_USER_TARGET_MINILOGUEXD;
+#[cfg(feature = "nutekt-digital")]
+pub const USER_TARGET_PLATFORM: u32 = K_USER_TARGET_NUTEKTDIGITAL;
+
+/// Name of the selected platform, as used in `manifest.json`.
+#[cfg(feature = "prologue")]
+pub const USER_TARGET_PLATFORM_NAME: &str = "prologue";
+#[cfg(feature = "minilogue-xd")]
+pub const USER_TARGET_PLATFORM_NAME: &str = "minilogue-xd";
+#[cfg(feature = "nutekt-digital")]
+pub const USER_TARGET_PLATFORM_NAME: &str = "nutekt-digital";
+
+pub const K_USER_API_1_1_0: u32 = (1<<16) | (1<<8);
+
+/// The API version units built with this crate are written against.
+pub const USER_API_VERSION: u32 = K_USER_API_1_1_0;
+
+/// The inverse of the 48,000 Hz sample rate used by all logue platforms.
+pub const K_SAMPLERATE_RECIP: f32 = 2.08333333333333e-005;
+
+/// SAMPLERATE_RECIP multiplied by 440.0, since FP math isn't allowed in const fns.
+pub const K_SR440: f32            = 9.16666666666666e-003;
+
+/// SAMPLERATE_RECIP multiplied by 220.0, since FP math isn't allowed in const fns.
+pub const K_SR220: f32            = 4.58333333333333e-003;
+
+pub fn osc_mcu_hash() -> u32 {
+    unsafe { _osc_mcu_hash() }
+}
+
+/// Zero the unit's `.bss` section. The firmware does not clear it when
+/// loading a unit, so this must be done from the initialization hook
+/// before any zero-initialized static is used.
+///
+/// # Safety
+///
+/// Must only be called at the start of the initialization hook, while
+/// no references to statics are live.
+#[cfg(not(feature = "host"))]
+pub unsafe fn clear_bss() {
+    let start = core::ptr::addr_of_mut!(_bss_start);
+    let end = core::ptr::addr_of_mut!(_bss_end);
+    core::ptr::write_bytes(start, 0, end as usize - start as usize);
+}
+
+/// On the host, statics start out zeroed and there is no `.bss` to
+/// clear.
+///
+/// # Safety
+///
+/// Always safe; `unsafe` to match the firmware version.
+#[cfg(feature = "host")]
+pub unsafe fn clear_bss() {}
@@ -0,0 +1,82 @@
+use core::mem;
+
+/// Magic bytes identifying a reverb effect hook table.
+pub const USER_REVFX_MAGIC: [u8; 4] = *b"UREV";
+
+#[repr(u8)]
+pub enum UserRevFxParamId {
+    /// Time parameter
+    Time = 0,
+    /// Depth parameter
+    Depth,
+    Reserved0,
+    /// Alternative depth parameter, usually accessible via shift function
+    ShiftDepth,
+}
+
+pub type InitCallback = unsafe extern "C" fn(platform: u32, api: u32);
+pub type ProcessCallback = unsafe extern "C" fn(xn: *mut f32, frames: u32);
+pub type SuspendCallback = unsafe extern "C" fn();
+pub type ResumeCallback = unsafe extern "C" fn();
+pub type ParamCallback = unsafe extern "C" fn(index: u8, value: i32);
+pub type DummyCallback = unsafe extern "C" fn();
+
+pub const DEFAULT_RESERVED0: [u8; 7] = [0; 7];
+pub const DEFAULT_RESERVED1: [u8; 7*mem::size_of::<DummyCallback>()] =
+    [0; 7*mem::size_of::<DummyCallback>()];
+
+#[repr(C)]
+#[repr(packed)]
+pub struct UserRevFxHookTable {
+    pub magic: [u8; 4],
+    pub api: u32,
+    pub platform: u8,
+    pub reserved0: [u8; 7],
+
+    /// Initialization callback. Must be implemented by your custom
+    /// effect.
+    ///
+    /// The `platform` parameter is the current target platform/module.
+    ///
+    /// The `api` parameter is the current API version.
+    pub func_entry: InitCallback,
+
+    /// Processing callback. Must be implemented by your custom effect.
+    ///
+    /// The `xn` parameter points to the input buffer (2 interleaved
+    /// samples per frame), to which the result must be written back.
+    /// Reverb effects are responsible for their own wet/dry balance,
+    /// which should typically be controlled by the shift-depth
+    /// parameter.
+    ///
+    /// The `frames` parameter holds the size of the buffer.
+    ///
+    /// The implementation must support at least up to 64 frames.
+    /// Optimize it for powers of two.
+    pub func_process: ProcessCallback,
+
+    /// Suspend callback. Must be implemented by your custom effect.
+    ///
+    /// Called before the effect is suspended. While suspended, the
+    /// processing callback will not be called.
+    pub func_suspend: SuspendCallback,
+
+    /// Resume callback. Must be implemented by your custom effect.
+    ///
+    /// Called before calls to the processing callback resume after being
+    /// suspended.
+    pub func_resume: ResumeCallback,
+
+    /// Parameter change callback. Must be implemented by your custom
+    /// effect.
+    ///
+    /// The parameter `index` contains the parameter ID (as in
+    /// `UserRevFxParamId`) and `value` contains the parameter value.
+    ///
+    /// All parameters have 10 bit resolution.
+    pub func_param: ParamCallback,
+
+    //pub reserved1: [DummyCallback; 7],
+    // Use bytes for the following so it can be zeroed
+    pub reserved1: [u8; 7*mem::size_of::<DummyCallback>()],
+}
@@ -0,0 +1,73 @@
+use core::cell::UnsafeCell;
+
+/// A buffer of `N` samples in the external SDRAM available to effect
+/// units, for delay lines and other state too large for SRAM. Declare
+/// one with `sdram_buffer!`, which places it in the `.sdram` section.
+///
+/// The linker scripts for effects reserve this section and fail the
+/// build when the buffers of a unit exceed its SDRAM budget (128K for
+/// modulation effects, 2432K for delays and reverbs). Oscillators
+/// cannot use SDRAM at all.
+///
+/// SDRAM is not part of the unit payload and is not cleared when the
+/// unit is loaded, so the contents are arbitrary until written. Clear
+/// the buffer from the initialization callback.
+#[repr(transparent)]
+pub struct SdramBuffer<const N: usize> {
+    buf: UnsafeCell<[f32; N]>,
+}
+
+// Hook callbacks can never be called concurrently.
+unsafe impl<const N: usize> Sync for SdramBuffer<N> {}
+
+impl<const N: usize> SdramBuffer<N> {
+    pub const fn new() -> Self {
+        SdramBuffer {
+            buf: UnsafeCell::new([0.0; N]),
+        }
+    }
+
+    /// Get the contents of the buffer.
+    ///
+    /// # Safety
+    ///
+    /// No other reference to the contents may be live. This holds if the
+    /// buffer is only used from the unit's hook callbacks, and references
+    /// do not outlive the callback that obtained them.
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn get_mut(&self) -> &mut [f32; N] {
+        &mut *self.buf.get()
+    }
+
+    /// Set all samples to zero.
+    ///
+    /// # Safety
+    ///
+    /// As for `get_mut`.
+    pub unsafe fn clear(&self) {
+        for x in self.get_mut().iter_mut() {
+            *x = 0.0;
+        }
+    }
+}
+
+impl<const N: usize> Default for SdramBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Declare a static `SdramBuffer` placed in SDRAM:
+///
+/// ```ignore
+/// logue::sdram_buffer!(static DELAY_LINE: [f32; 48000 * 2]);
+/// ```
+#[macro_export]
+macro_rules! sdram_buffer {
+    ($(#[$attr:meta])* $vis:vis static $name:ident: [f32; $n:expr]) => {
+        $(#[$attr])*
+        #[link_section = ".sdram"]
+        $vis static $name: $crate::sdram::SdramBuffer<{ $n }> =
+            $crate::sdram::SdramBuffer::new();
+    };
+}
@@ -0,0 +1,336 @@
+use core::convert::TryFrom;
+use core::{fmt, mem};
+
+/// Magic bytes identifying an oscillator hook table.
+pub const USER_OSC_MAGIC: [u8; 4] = *b"UOSC";
+
+#[repr(C)]
+pub struct UserOscParams {
+    /// Value of LFO implicitly applied to shape parameter.
+    pub shape_lfo: i32,
+    /// Current pitch. High byte: note number. Low byte: fine (0-255).
+    pub pitch: u16,
+    /// Current cutoff value (0x0000-0x1fff).
+    pub cutoff: u16,
+    /// Current resonance value (0x0000-0x1fff)
+    pub resonance: u16,
+    pub reserved0: [u16; 3],
+}
+
+/// Parameter IDs, as passed to the parameter change callback.
+///
+/// The callback receives the raw index; convert it with `TryFrom<u16>`,
+/// which fails with `UnknownParamId` for indices this crate does not
+/// know about rather than trusting the firmware to only send valid ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UserOscParamId {
+    /// Edit parameter 1
+    Id1,
+    /// Edit parameter 2
+    Id2,
+    /// Edit parameter 3
+    Id3,
+    /// Edit parameter 4
+    Id4,
+    /// Edit parameter 5
+    Id5,
+    /// Edit parameter 6
+    Id6,
+    /// Shape parameter
+    Shape,
+    ///  Alternative Shape parameter: generally available via a shift function
+    ShiftShape,
+}
+
+/// A parameter index outside those of `UserOscParamId`, such as one of a
+/// newer API.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnknownParamId(pub u16);
+
+impl fmt::Display for UnknownParamId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown parameter index {}", self.0)
+    }
+}
+
+impl TryFrom<u16> for UserOscParamId {
+    type Error = UnknownParamId;
+
+    fn try_from(index: u16) -> Result<Self, UnknownParamId> {
+        Ok(match index {
+            0 => UserOscParamId::Id1,
+            1 => UserOscParamId::Id2,
+            2 => UserOscParamId::Id3,
+            3 => UserOscParamId::Id4,
+            4 => UserOscParamId::Id5,
+            5 => UserOscParamId::Id6,
+            6 => UserOscParamId::Shape,
+            7 => UserOscParamId::ShiftShape,
+            _ => return Err(UnknownParamId(index)),
+        })
+    }
+}
+
+impl From<UserOscParamId> for u16 {
+    fn from(id: UserOscParamId) -> Self {
+        match id {
+            UserOscParamId::Id1 => 0,
+            UserOscParamId::Id2 => 1,
+            UserOscParamId::Id3 => 2,
+            UserOscParamId::Id4 => 3,
+            UserOscParamId::Id5 => 4,
+            UserOscParamId::Id6 => 5,
+            UserOscParamId::Shape => 6,
+            UserOscParamId::ShiftShape => 7,
+        }
+    }
+}
+
+/// Convert 10-bit parameter value to f32
+pub fn param_val_to_f32(x: u16) -> f32 {
+    x as f32 * 9.77517106549365e-004f32
+}
+
+pub type InitCallback = unsafe extern "C" fn(platform: u32, api: u32);
+pub type CycleCallback = unsafe extern "C" fn(params: &UserOscParams, yn: *mut i32, frames: u32);
+pub type OnCallback = unsafe extern "C" fn(params: &UserOscParams);
+pub type OffCallback = unsafe extern "C" fn(params: &UserOscParams);
+pub type MuteCallback = unsafe extern "C" fn(params: &UserOscParams);
+pub type ValueCallback = unsafe extern "C" fn(value: u16);
+pub type ParamCallback = unsafe extern "C" fn(index: u16, value: u16);
+pub type DummyCallback = unsafe extern "C" fn();
+
+pub const DEFAULT_RESERVED0: [u8; 7] = [0; 7];
+pub const DEFAULT_RESERVED1: [u8; 5*mem::size_of::<DummyCallback>()] =
+    [0; 5*mem::size_of::<DummyCallback>()];
+
+#[repr(C)]
+#[repr(packed)]
+pub struct UserOscHookTable {
+    pub magic: [u8; 4],
+    pub api: u32,
+    pub platform: u8,
+    pub reserved0: [u8; 7],
+
+    /// Initialization callback. Must be implemented by your custom
+    /// oscillator.
+    ///
+    /// The `platform` parameter is the current target platform/module.
+    ///
+    /// The `api` parameter is the current API version.
+    pub func_entry: InitCallback,
+
+    /// Rendering callback. Must be implemented by your custom oscillator.
+    ///
+    /// The `params` parameter contains the current realtime parameter
+    /// state.
+    ///
+    /// The `yn` parameter points to the output buffer (1 sample per
+    /// frame).
+    ///
+    /// The `frames` parameter holds the size of the output buffer.
+    ///
+    /// The implementation must support at least up to 64 frames.
+    /// Optimize it for powers of two.
+    pub func_cycle: CycleCallback,
+
+    /// Note on callback. Must be implemented by your custom oscillator.
+    ///
+    /// The `params` parameter contains the current realtime parameter
+    /// state.
+    pub func_on: OnCallback,
+
+    /// Note off callback. Must be implemented by your custom
+    /// oscillator.
+    ///
+    /// The `params` parameter contains the current realtime parameter
+    /// state.
+    pub func_off: OffCallback,
+
+    pub func_mute: MuteCallback,
+
+    pub func_value: ValueCallback,
+
+    /// Parameter change callback. Must be implemented by your custom
+    /// oscillator.
+    ///
+    /// The parameter `index` contains the parameter ID (as in
+    /// `UserOscParamId`) and `value` contains the parameter value.
+    ///
+    /// Resolution is 10 bits for shape and shift-shape, 0-200 for
+    /// bipolar percentage paramters (0% at 100, -100% at 0) and 0-11
+    /// for unipolar percentage parameters and typeless parameters.
+    pub func_param: ParamCallback,
+
+    //pub reserved1: [DummyCallback; 5],
+    // Use bytes for the following so it can be zeroed
+    pub reserved1: [u8; 5*mem::size_of::<DummyCallback>()],
+}
+
+/// A custom oscillator. Implement this trait and pass the type to
+/// `declare_oscillator!` to generate the hook table and callbacks the
+/// firmware expects.
+///
+/// The methods correspond to the callbacks of `UserOscHookTable`, which
+/// documents them in more detail.
+pub trait Oscillator {
+    /// Initialize the oscillator. `platform` is the current target
+    /// platform/module and `api` the current API version.
+    fn init(&mut self, platform: u32, api: u32);
+
+    /// Render `yn.len()` samples (at most 64) to `yn`.
+    fn cycle(&mut self, params: &UserOscParams, yn: &mut [i32]);
+
+    /// Note on.
+    fn note_on(&mut self, _params: &UserOscParams) {}
+
+    /// Note off.
+    fn note_off(&mut self, _params: &UserOscParams) {}
+
+    fn mute(&mut self, _params: &UserOscParams) {}
+
+    fn value(&mut self, _value: u16) {}
+
+    /// Parameter change. Changes with unknown indices are not passed on.
+    fn param(&mut self, index: UserOscParamId, value: u16);
+}
+
+/// Declare the oscillator unit implemented by a type implementing
+/// `Oscillator`. This emits the `.hooks` section table tagged with the
+/// platform selected for the `logue` crate, a single static instance
+/// of the type and the callbacks forwarding to it.
+///
+/// The instance is created with `<Type>::new()`, which must be a
+/// `const fn`, or with the given constant expression:
+///
+/// ```ignore
+/// logue::declare_oscillator!(MyOsc);
+/// logue::declare_oscillator!(MyOsc = MyOsc::with_voices(2));
+/// ```
+///
+/// The oscillator is only initialized and called if the target and API
+/// version passed to the initialization callback are compatible with
+/// those the unit was built for (see `userprg::runtime_is_compat`).
+/// Otherwise the unit refuses to run and renders silence. Parameter
+/// changes with unknown indices are ignored.
+///
+/// A unit binary must contain exactly one such declaration.
+#[macro_export]
+macro_rules! declare_oscillator {
+    ($ty:ty) => {
+        $crate::declare_oscillator!($ty = <$ty>::new());
+    };
+    ($ty:ty = $init:expr) => {
+        const _: () = {
+            use ::core::convert::TryFrom;
+            use $crate::userosc::{Oscillator, UserOscHookTable, UserOscParamId, UserOscParams};
+
+            /// Global oscillator state. Safe to access from the functions
+            /// below because they can never be called concurrently.
+            static mut S_OSC: $ty = $init;
+
+            /// Whether the runtime can run this unit. Set by `_hook_init`.
+            static mut S_COMPAT: bool = false;
+
+            unsafe fn osc() -> Option<&'static mut $ty> {
+                if S_COMPAT {
+                    Some(&mut *::core::ptr::addr_of_mut!(S_OSC))
+                } else {
+                    None
+                }
+            }
+
+            #[used]
+            #[no_mangle]
+            #[link_section = ".hooks"]
+            static s_hook_table: UserOscHookTable =
+                UserOscHookTable {
+                    magic: $crate::userosc::USER_OSC_MAGIC,
+                    api: $crate::platform::USER_API_VERSION,
+                    platform: ($crate::platform::USER_TARGET_PLATFORM>>8) as u8,
+                    reserved0: $crate::userosc::DEFAULT_RESERVED0,
+                    func_entry: _hook_init,
+                    func_cycle: _hook_cycle,
+                    func_on: _hook_on,
+                    func_off: _hook_off,
+                    func_mute: _hook_mute,
+                    func_value: _hook_value,
+                    func_param: _hook_param,
+                    reserved1: $crate::userosc::DEFAULT_RESERVED1,
+                };
+
+            #[no_mangle]
+            unsafe extern "C" fn _hook_init(platform: u32, api: u32) {
+                $crate::platform::clear_bss();
+                S_COMPAT = $crate::userprg::runtime_is_compat($crate::userprg::K_USER_MODULE_OSC, platform, api);
+                if let Some(osc) = osc() {
+                    Oscillator::init(osc, platform, api);
+                }
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn _hook_cycle(params: &UserOscParams, yn: *mut i32, frames: u32) {
+                let yn = ::core::slice::from_raw_parts_mut(yn, frames as usize);
+                match osc() {
+                    Some(osc) => Oscillator::cycle(osc, params, yn),
+                    None => yn.iter_mut().for_each(|y| *y = 0),
+                }
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn _hook_on(params: &UserOscParams) {
+                if let Some(osc) = osc() {
+                    Oscillator::note_on(osc, params);
+                }
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn _hook_off(params: &UserOscParams) {
+                if let Some(osc) = osc() {
+                    Oscillator::note_off(osc, params);
+                }
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn _hook_mute(params: &UserOscParams) {
+                if let Some(osc) = osc() {
+                    Oscillator::mute(osc, params);
+                }
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn _hook_value(value: u16) {
+                if let Some(osc) = osc() {
+                    Oscillator::value(osc, value);
+                }
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn _hook_param(index: u16, value: u16) {
+                if let (Some(osc), Ok(index)) = (osc(), UserOscParamId::try_from(index)) {
+                    Oscillator::param(osc, index, value);
+                }
+            }
+        };
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn param_ids() {
+        for index in 0..8 {
+            let id = UserOscParamId::try_from(index).unwrap();
+            assert_eq!(u16::from(id), index);
+        }
+        assert_eq!(UserOscParamId::try_from(0), Ok(UserOscParamId::Id1));
+        assert_eq!(UserOscParamId::try_from(5), Ok(UserOscParamId::Id6));
+        assert_eq!(UserOscParamId::try_from(6), Ok(UserOscParamId::Shape));
+        assert_eq!(UserOscParamId::try_from(7), Ok(UserOscParamId::ShiftShape));
+        for index in [8, 9, 0xff, 0xffff] {
+            assert_eq!(UserOscParamId::try_from(index), Err(UnknownParamId(index)));
+        }
+    }
+}
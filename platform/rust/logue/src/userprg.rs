@@ -0,0 +1,399 @@
+use core::mem;
+
+use crate::platform::*;
+
+/// Module categories.
+pub const K_USER_MODULE_GLOBAL: u32 = 0;
+pub const K_USER_MODULE_MODFX: u32  = 1;
+pub const K_USER_MODULE_DELFX: u32  = 2;
+pub const K_USER_MODULE_REVFX: u32  = 3;
+pub const K_USER_MODULE_OSC: u32    = 4;
+
+pub const USER_TARGET_PLATFORM_MASK: u32 = 0x7F<<8;
+pub const USER_TARGET_MODULE_MASK: u32   = 0x7F;
+
+pub const USER_API_MAJOR_MASK: u32 = 0x7F<<16;
+pub const USER_API_MINOR_MASK: u32 = 0x7F<<8;
+pub const USER_API_PATCH_MASK: u32 = 0x7F;
+
+/// A platform/module pair, as passed to the initialization callback.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Target(pub u32);
+
+impl Target {
+    pub const fn new(platform: u32, module: u32) -> Self {
+        Target((platform & USER_TARGET_PLATFORM_MASK) | (module & USER_TARGET_MODULE_MASK))
+    }
+
+    /// The platform, as one of the `K_USER_TARGET_*` constants.
+    pub const fn platform(self) -> u32 {
+        self.0 & USER_TARGET_PLATFORM_MASK
+    }
+
+    /// The module, as one of the `K_USER_MODULE_*` constants.
+    pub const fn module(self) -> u32 {
+        self.0 & USER_TARGET_MODULE_MASK
+    }
+
+    /// Whether the platform is one that can run logue units, as in
+    /// `USER_TARGET_PLATFORM_IS_COMPAT`.
+    pub const fn is_compat(self) -> bool {
+        let platform = self.platform();
+        platform == K_USER_TARGET_PROLOGUE
+            || platform == K_USER_TARGET_MINILOGUEXD
+            || platform == K_USER_TARGET_NUTEKTDIGITAL
+    }
+}
+
+/// An API version. Major versions contain breaking changes, minor
+/// versions additions only and patch versions bug fixes only. Each
+/// component is 7 bits wide.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ApiVersion(pub u32);
+
+impl ApiVersion {
+    pub const fn new(major: u8, minor: u8, patch: u8) -> Self {
+        ApiVersion((((major as u32) << 16) & USER_API_MAJOR_MASK)
+                   | (((minor as u32) << 8) & USER_API_MINOR_MASK)
+                   | ((patch as u32) & USER_API_PATCH_MASK))
+    }
+
+    pub const fn major(self) -> u8 {
+        ((self.0 & USER_API_MAJOR_MASK) >> 16) as u8
+    }
+
+    pub const fn minor(self) -> u8 {
+        ((self.0 & USER_API_MINOR_MASK) >> 8) as u8
+    }
+
+    pub const fn patch(self) -> u8 {
+        (self.0 & USER_API_PATCH_MASK) as u8
+    }
+
+    /// Whether code targeting `api` can run against an implementation of
+    /// this version: the major versions must match, and `api` must not
+    /// depend on additions from a later minor version. This is
+    /// `USER_API_IS_COMPAT`, with `self` in place of `USER_API_VERSION`.
+    pub const fn is_compat(self, api: ApiVersion) -> bool {
+        self.major() == api.major() && api.minor() <= self.minor()
+    }
+}
+
+/// Whether a unit of `module`, built with this crate, can run on the
+/// runtime described by the arguments of its initialization callback:
+/// the platform must run logue units, the module must be the unit's,
+/// and the API version must provide the one the crate targets.
+///
+/// Units declared with `declare_oscillator!` check this when they are
+/// initialized, and refuse to run (rendering silence) on an incompatible
+/// runtime.
+pub const fn runtime_is_compat(module: u32, platform: u32, api: u32) -> bool {
+    let target = Target(platform);
+    target.is_compat() && target.module() == module
+        && ApiVersion(api).is_compat(ApiVersion(USER_API_VERSION))
+}
+
+pub const USER_PRG_MAX_PARAM_COUNT: usize = 6;
+pub const USER_PRG_PARAM_MIN_LIMIT: i32   = -100;
+pub const USER_PRG_PARAM_MAX_LIMIT: i32   = 100;
+pub const USER_PRG_PARAM_NAME_LEN: usize  = 12;
+pub const USER_PRG_NAME_LEN: usize        = 13;
+
+pub const USER_PRG_HEADER_SIZE: usize = 0x400;
+/// Can fit an ECDSA signature using secp521r1.
+pub const USER_PRG_SIG_SIZE: usize    = 0x84;
+
+/// Parameter types.
+pub const K_USER_PRG_PARAM_TYPE_PERCENT: u8         = 0;
+pub const K_USER_PRG_PARAM_TYPE_PERCENT_BIPOLAR: u8 = 1;
+pub const K_USER_PRG_PARAM_TYPE_SELECT: u8          = 2;
+
+/// An edit menu parameter, as described in a program header.
+#[repr(C)]
+#[repr(packed)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserPrgParam {
+    pub min: i8,
+    pub max: i8,
+    /// One of the `K_USER_PRG_PARAM_TYPE_*` constants.
+    pub param_type: u8,
+    /// NUL-terminated name.
+    pub name: [u8; USER_PRG_PARAM_NAME_LEN+1],
+}
+
+const USER_PRG_PARAM_SIZE: usize = mem::size_of::<UserPrgParam>();
+
+impl UserPrgParam {
+    pub fn to_bytes(&self) -> [u8; USER_PRG_PARAM_SIZE] {
+        let mut bytes = [0; USER_PRG_PARAM_SIZE];
+        bytes[0] = self.min as u8;
+        bytes[1] = self.max as u8;
+        bytes[2] = self.param_type;
+        bytes[3..].copy_from_slice(&self.name);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; USER_PRG_PARAM_SIZE]) -> Self {
+        let mut name = [0; USER_PRG_PARAM_NAME_LEN+1];
+        name.copy_from_slice(&bytes[3..]);
+        UserPrgParam {
+            min: bytes[0] as i8,
+            max: bytes[1] as i8,
+            param_type: bytes[2],
+            name,
+        }
+    }
+}
+
+/// The header preceding a unit's code in the program image sent to the
+/// synthesizer. All fields are little endian.
+#[repr(C)]
+#[repr(packed)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserPrgHeader {
+    /// Platform and module, as in `Target`.
+    pub target: u16,
+    pub api: u32,
+    pub dev_id: u32,
+    pub prg_id: u32,
+    /// Version of the unit, encoded like an API version.
+    pub version: u32,
+    /// NUL-terminated name.
+    pub name: [u8; USER_PRG_NAME_LEN+1],
+    pub num_param: u32,
+    pub params: [UserPrgParam; USER_PRG_MAX_PARAM_COUNT],
+    pub pad: [u8; USER_PRG_HEADER_SIZE-40-USER_PRG_MAX_PARAM_COUNT*USER_PRG_PARAM_SIZE],
+    /// Size of the code following the header.
+    pub load_size: u32,
+}
+
+const _: () = assert!(USER_PRG_PARAM_SIZE == 16);
+const _: () = assert!(mem::size_of::<UserPrgHeader>() == USER_PRG_HEADER_SIZE);
+
+/// Offsets of the header fields following the name.
+const HEADER_NUM_PARAM: usize = 18 + USER_PRG_NAME_LEN+1;
+const HEADER_PARAMS: usize = HEADER_NUM_PARAM + 4;
+const HEADER_PAD: usize = HEADER_PARAMS + USER_PRG_MAX_PARAM_COUNT*USER_PRG_PARAM_SIZE;
+const HEADER_LOAD_SIZE: usize = USER_PRG_HEADER_SIZE - 4;
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut b = [0; 4];
+    b.copy_from_slice(&bytes[offset..offset+4]);
+    u32::from_le_bytes(b)
+}
+
+impl UserPrgHeader {
+    pub fn to_bytes(&self) -> [u8; USER_PRG_HEADER_SIZE] {
+        let mut bytes = [0; USER_PRG_HEADER_SIZE];
+        let params = self.params;
+        bytes[0..2].copy_from_slice(&{ self.target }.to_le_bytes());
+        bytes[2..6].copy_from_slice(&{ self.api }.to_le_bytes());
+        bytes[6..10].copy_from_slice(&{ self.dev_id }.to_le_bytes());
+        bytes[10..14].copy_from_slice(&{ self.prg_id }.to_le_bytes());
+        bytes[14..18].copy_from_slice(&{ self.version }.to_le_bytes());
+        bytes[18..HEADER_NUM_PARAM].copy_from_slice(&self.name);
+        bytes[HEADER_NUM_PARAM..HEADER_PARAMS].copy_from_slice(&{ self.num_param }.to_le_bytes());
+        for (i, p) in params.iter().enumerate() {
+            let offset = HEADER_PARAMS + i*USER_PRG_PARAM_SIZE;
+            bytes[offset..offset+USER_PRG_PARAM_SIZE].copy_from_slice(&p.to_bytes());
+        }
+        bytes[HEADER_PAD..HEADER_LOAD_SIZE].copy_from_slice(&self.pad);
+        bytes[HEADER_LOAD_SIZE..].copy_from_slice(&{ self.load_size }.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; USER_PRG_HEADER_SIZE]) -> Self {
+        let mut name = [0; USER_PRG_NAME_LEN+1];
+        name.copy_from_slice(&bytes[18..HEADER_NUM_PARAM]);
+        let mut params = [UserPrgParam::from_bytes(&[0; USER_PRG_PARAM_SIZE]); USER_PRG_MAX_PARAM_COUNT];
+        for (i, p) in params.iter_mut().enumerate() {
+            let offset = HEADER_PARAMS + i*USER_PRG_PARAM_SIZE;
+            let mut b = [0; USER_PRG_PARAM_SIZE];
+            b.copy_from_slice(&bytes[offset..offset+USER_PRG_PARAM_SIZE]);
+            *p = UserPrgParam::from_bytes(&b);
+        }
+        let mut pad = [0; USER_PRG_HEADER_SIZE-40-USER_PRG_MAX_PARAM_COUNT*USER_PRG_PARAM_SIZE];
+        pad.copy_from_slice(&bytes[HEADER_PAD..HEADER_LOAD_SIZE]);
+        UserPrgHeader {
+            target: u16::from_le_bytes([bytes[0], bytes[1]]),
+            api: read_u32(bytes, 2),
+            dev_id: read_u32(bytes, 6),
+            prg_id: read_u32(bytes, 10),
+            version: read_u32(bytes, 14),
+            name,
+            num_param: read_u32(bytes, HEADER_NUM_PARAM),
+            params,
+            pad,
+            load_size: read_u32(bytes, HEADER_LOAD_SIZE),
+        }
+    }
+}
+
+/// The signature following a unit's code in the program image.
+#[repr(C)]
+#[repr(packed)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserPrgSig {
+    pub pad: [u8; USER_PRG_SIG_SIZE],
+}
+
+const _: () = assert!(mem::size_of::<UserPrgSig>() == USER_PRG_SIG_SIZE);
+
+impl UserPrgSig {
+    pub fn to_bytes(&self) -> [u8; USER_PRG_SIG_SIZE] {
+        self.pad
+    }
+
+    pub fn from_bytes(bytes: &[u8; USER_PRG_SIG_SIZE]) -> Self {
+        UserPrgSig { pad: *bytes }
+    }
+}
+
+impl Default for UserPrgHeader {
+    fn default() -> Self {
+        UserPrgHeader::from_bytes(&[0; USER_PRG_HEADER_SIZE])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_versions() {
+        let v = ApiVersion::new(1, 2, 3);
+        assert_eq!((v.major(), v.minor(), v.patch()), (1, 2, 3));
+        assert_eq!(v, ApiVersion(0x01_02_03));
+    }
+
+    #[test]
+    fn api_compat() {
+        let runtime = ApiVersion::new(1, 2, 0);
+        assert!(runtime.is_compat(ApiVersion::new(1, 2, 0)));
+        assert!(runtime.is_compat(ApiVersion::new(1, 1, 7)));
+        // A unit needing additions of a newer minor version.
+        assert!(!runtime.is_compat(ApiVersion::new(1, 3, 0)));
+        // Major versions must match either way.
+        assert!(!runtime.is_compat(ApiVersion::new(2, 0, 0)));
+        assert!(!runtime.is_compat(ApiVersion::new(0, 2, 0)));
+    }
+
+    #[test]
+    fn targets() {
+        let t = Target::new(K_USER_TARGET_MINILOGUEXD, K_USER_MODULE_OSC);
+        assert_eq!((t.platform(), t.module()), (K_USER_TARGET_MINILOGUEXD, K_USER_MODULE_OSC));
+        for platform in [K_USER_TARGET_PROLOGUE, K_USER_TARGET_MINILOGUEXD, K_USER_TARGET_NUTEKTDIGITAL] {
+            assert!(Target::new(platform, K_USER_MODULE_OSC).is_compat());
+        }
+        assert!(!Target::new(0, K_USER_MODULE_OSC).is_compat());
+        assert!(!Target::new(4 << 8, K_USER_MODULE_OSC).is_compat());
+    }
+
+    #[test]
+    fn runtime_compat() {
+        let osc = K_USER_TARGET_NUTEKTDIGITAL | K_USER_MODULE_OSC;
+        assert!(runtime_is_compat(K_USER_MODULE_OSC, osc, USER_API_VERSION));
+        // A newer minor version of the runtime still runs the unit.
+        assert!(runtime_is_compat(K_USER_MODULE_OSC, osc, USER_API_VERSION + (1 << 8)));
+        // An older one may lack what the unit uses.
+        assert!(!runtime_is_compat(K_USER_MODULE_OSC, osc, ApiVersion::new(1, 0, 0).0));
+        assert!(!runtime_is_compat(K_USER_MODULE_OSC, osc, ApiVersion::new(2, 1, 0).0));
+        // Wrong or missing platforms.
+        assert!(!runtime_is_compat(K_USER_MODULE_OSC, K_USER_MODULE_OSC, USER_API_VERSION));
+        assert!(!runtime_is_compat(K_USER_MODULE_OSC, 5 << 8 | K_USER_MODULE_OSC, USER_API_VERSION));
+        // An oscillator loaded as another kind of unit.
+        let modfx = K_USER_TARGET_NUTEKTDIGITAL | K_USER_MODULE_MODFX;
+        assert!(!runtime_is_compat(K_USER_MODULE_OSC, modfx, USER_API_VERSION));
+        assert!(runtime_is_compat(K_USER_MODULE_MODFX, modfx, USER_API_VERSION));
+    }
+
+    fn name<const N: usize>(s: &str) -> [u8; N] {
+        let mut name = [0; N];
+        name[..s.len()].copy_from_slice(s.as_bytes());
+        name
+    }
+
+    /// A byte pattern with no repeats within 256 bytes.
+    fn pattern<const N: usize>() -> [u8; N] {
+        let mut bytes = [0; N];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = (i * 7 + i / 256) as u8;
+        }
+        bytes
+    }
+
+    #[test]
+    fn param_layout() {
+        // As `user_prg_param_t` in userprg.h.
+        assert_eq!(mem::size_of::<UserPrgParam>(), 16);
+        assert_eq!(mem::offset_of!(UserPrgParam, min), 0);
+        assert_eq!(mem::offset_of!(UserPrgParam, max), 1);
+        assert_eq!(mem::offset_of!(UserPrgParam, param_type), 2);
+        assert_eq!(mem::offset_of!(UserPrgParam, name), 3);
+
+        let p = UserPrgParam { min: -100, max: 100, param_type: K_USER_PRG_PARAM_TYPE_PERCENT_BIPOLAR,
+                               name: name("Drive") };
+        let bytes = p.to_bytes();
+        assert_eq!(bytes[..8], [0x9c, 100, 1, b'D', b'r', b'i', b'v', b'e']);
+        assert_eq!(bytes[8..], [0; 8]);
+        assert_eq!(UserPrgParam::from_bytes(&bytes), p);
+        let bytes = pattern();
+        assert_eq!(UserPrgParam::from_bytes(&bytes).to_bytes(), bytes);
+    }
+
+    #[test]
+    fn header_layout() {
+        // As `user_prg_header_t` in userprg.h.
+        assert_eq!(mem::size_of::<UserPrgHeader>(), 0x400);
+        assert_eq!(mem::offset_of!(UserPrgHeader, target), 0);
+        assert_eq!(mem::offset_of!(UserPrgHeader, api), 2);
+        assert_eq!(mem::offset_of!(UserPrgHeader, dev_id), 6);
+        assert_eq!(mem::offset_of!(UserPrgHeader, prg_id), 10);
+        assert_eq!(mem::offset_of!(UserPrgHeader, version), 14);
+        assert_eq!(mem::offset_of!(UserPrgHeader, name), 18);
+        assert_eq!(mem::offset_of!(UserPrgHeader, num_param), 32);
+        assert_eq!(mem::offset_of!(UserPrgHeader, params), 36);
+        assert_eq!(mem::offset_of!(UserPrgHeader, pad), 132);
+        assert_eq!(mem::offset_of!(UserPrgHeader, load_size), 0x3fc);
+        assert_eq!((HEADER_NUM_PARAM, HEADER_PARAMS, HEADER_PAD, HEADER_LOAD_SIZE), (32, 36, 132, 0x3fc));
+    }
+
+    #[test]
+    fn header_bytes() {
+        let mut header = UserPrgHeader {
+            target: (K_USER_TARGET_NUTEKTDIGITAL | K_USER_MODULE_OSC) as u16,
+            api: USER_API_VERSION,
+            dev_id: 0x0403_0201,
+            prg_id: 0x0807_0605,
+            version: ApiVersion::new(1, 0, 3).0,
+            name: name("raves"),
+            num_param: 2,
+            load_size: 0x0c0b_0a09,
+            ..UserPrgHeader::default()
+        };
+        header.params[1] = UserPrgParam { min: 0, max: 45, param_type: K_USER_PRG_PARAM_TYPE_SELECT,
+                                          name: name("Wave B") };
+        let bytes = header.to_bytes();
+        assert_eq!(bytes[0..2], [0x04, 0x03]);
+        assert_eq!(bytes[2..6], [0x00, 0x01, 0x01, 0x00]);
+        assert_eq!(bytes[6..18], [1, 2, 3, 4, 5, 6, 7, 8, 3, 0, 1, 0]);
+        assert_eq!(bytes[18..32], *b"raves\0\0\0\0\0\0\0\0\0");
+        assert_eq!(bytes[32..36], [2, 0, 0, 0]);
+        assert_eq!(bytes[36..52], [0; 16]);
+        assert_eq!(bytes[52..56], [0, 45, 2, b'W']);
+        assert!(bytes[68..0x3fc].iter().all(|&b| b == 0));
+        assert_eq!(bytes[0x3fc..], [9, 10, 11, 12]);
+        assert_eq!(UserPrgHeader::from_bytes(&bytes), header);
+
+        let bytes = pattern();
+        assert_eq!(UserPrgHeader::from_bytes(&bytes).to_bytes(), bytes);
+    }
+
+    #[test]
+    fn sig_bytes() {
+        assert_eq!(mem::size_of::<UserPrgSig>(), 0x84);
+        let bytes = pattern();
+        assert_eq!(UserPrgSig::from_bytes(&bytes).to_bytes(), bytes);
+        assert_eq!(UserPrgSig::from_bytes(&bytes).pad, bytes);
+    }
+}
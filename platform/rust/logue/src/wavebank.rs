@@ -18,6 +18,7 @@ pub const K_WAVES_FRRECIP : f32 = 5.96046447753906e-008;
 pub const K_WAVES_MASK : usize = K_WAVES_SIZE - 1;
 pub const K_WAVES_LUT_SIZE : usize = K_WAVES_SIZE + 1;
 
+// The wave banks are identical on all logue platforms.
 pub const K_WAVES_A_CNT : usize = 16;
 pub const K_WAVES_B_CNT : usize = 16;
 pub const K_WAVES_C_CNT : usize = 14;
@@ -25,8 +26,14 @@ pub const K_WAVES_D_CNT : usize = 13;
 pub const K_WAVES_E_CNT : usize = 15;
 pub const K_WAVES_F_CNT : usize = 16;
 
-pub fn wave_table_ref(p: *const WaveLUT) -> &'static WaveLUT {
-    unsafe { &*p }
+/// Dereference a wave table pointer obtained from one of the
+/// `get_waves_*_elt` functions.
+///
+/// # Safety
+///
+/// `p` must be a non-null pointer into the firmware wave banks.
+pub unsafe fn wave_table_ref(p: *const WaveLUT) -> &'static WaveLUT {
+    &*p
 }
 
 pub fn get_waves_a_elt(idx: usize) -> *const WaveLUT {
@@ -58,7 +65,7 @@ pub fn osc_wave_scanf(w: &WaveLUT, x: f32) -> f32 {
     let x0f = p * K_WAVES_SIZE as f32;
     let x0 = x0f as usize & K_WAVES_MASK;
     let x1 = (x0 + 1) & K_WAVES_MASK;
-    return linintf(x0f - (x0f as u32) as f32, w[x0], w[x1]);
+    linintf(x0f - (x0f as u32) as f32, w[x0], w[x1])
 }
 
 pub fn osc_wave_scanuf(w: &WaveLUT, x: u32) -> f32 {
@@ -66,5 +73,5 @@ pub fn osc_wave_scanuf(w: &WaveLUT, x: u32) -> f32 {
     let x0 = xu >> K_WAVES_U32_SHIFT;
     let x1 = (x0 + 1) & K_WAVES_MASK;
     let fr = K_WAVES_FRRECIP * ((x & ((1 << K_WAVES_U32_SHIFT) - 1)) as f32);
-    return linintf(fr, w[x0], w[x1]);
+    linintf(fr, w[x0], w[x1])
 }
@@ -0,0 +1,102 @@
+//! The lookup table helpers at the edges of their domains, against the
+//! `host` stand-ins for the firmware's tables.
+
+use logue::fx_api::*;
+use logue::host::{bitres_lut_f, cubicsat_lut_f, schetzen_lut_f, tanpi_lut_f};
+use logue::*;
+
+/// Inputs at the start of every domain or below it, and above it.
+const BELOW: [f32; 4] = [-0.0, -0.25, -1.0, f32::NEG_INFINITY];
+const ABOVE: [f32; 3] = [1.5, 1e9, f32::INFINITY];
+
+#[test]
+fn bitres_ends_of_domain() {
+    assert_eq!(osc_bitresf(0.0), bitres_lut_f[0]);
+    assert_eq!(osc_bitresf(1.0), bitres_lut_f[K_BITRES_SIZE]);
+}
+
+#[test]
+fn bitres_clamps_to_domain() {
+    for x in BELOW.iter().chain([f32::NAN].iter()) {
+        assert_eq!(osc_bitresf(*x), osc_bitresf(0.0), "osc_bitresf({})", x);
+    }
+    for x in ABOVE {
+        assert_eq!(osc_bitresf(x), osc_bitresf(1.0), "osc_bitresf({})", x);
+    }
+}
+
+#[test]
+fn bitres_falls_across_domain() {
+    let mut last = f32::INFINITY;
+    for i in 0..=1000 {
+        let y = osc_bitresf(i as f32 / 1000.0);
+        assert!(y <= last && y >= 1.0, "osc_bitresf({}) = {}", i as f32 / 1000.0, y);
+        last = y;
+    }
+}
+
+#[test]
+fn tanpi_ends_of_domain() {
+    assert_eq!(osc_tanpif(0.0), tanpi_lut_f[0]);
+    let top = osc_tanpif(0.49);
+    let expected = (core::f32::consts::PI * 0.49).tan();
+    assert!((top - expected).abs() < expected * 1e-3, "osc_tanpif(0.49) = {}, not {}", top, expected);
+    assert!((osc_tanpif(0.25) - 1.0).abs() < 1e-3);
+}
+
+#[test]
+fn tanpi_clamps_to_domain() {
+    for x in BELOW.iter().chain([f32::NAN].iter()) {
+        assert_eq!(osc_tanpif(*x), osc_tanpif(0.0), "osc_tanpif({})", x);
+    }
+    for x in [0.5, 1.0].iter().chain(ABOVE.iter()) {
+        assert_eq!(osc_tanpif(*x), osc_tanpif(0.49), "osc_tanpif({})", x);
+    }
+}
+
+#[test]
+fn fx_bitres_and_tanpi_match_osc() {
+    for x in BELOW.iter().chain(ABOVE.iter()).chain([f32::NAN, 0.0, 0.25, 0.49, 1.0].iter()) {
+        assert_eq!(fx_bitresf(*x), osc_bitresf(*x), "fx_bitresf({})", x);
+        assert_eq!(fx_tanpif(*x), osc_tanpif(*x), "fx_tanpif({})", x);
+    }
+}
+
+/// A saturation curve, with the table it looks up.
+type Saturation = (fn(f32) -> f32, &'static [f32]);
+
+const SATURATION: [Saturation; 2] = [(fx_sat_cubicf, &cubicsat_lut_f), (fx_sat_schetzenf, &schetzen_lut_f)];
+
+#[test]
+fn saturation_ends_of_domain() {
+    for (sat, lut) in SATURATION {
+        assert_eq!(sat(0.0), 0.0);
+        assert!(sat(-0.0).is_sign_negative());
+        assert_eq!(sat(1.0), lut[lut.len() - 1]);
+        assert_eq!(sat(-1.0), -lut[lut.len() - 1]);
+        assert!((sat(1.0) - 1.0).abs() < 1e-5, "saturating 1 gives {}", sat(1.0));
+    }
+}
+
+#[test]
+fn saturation_clamps_to_domain() {
+    for (sat, _) in SATURATION {
+        for x in ABOVE {
+            assert_eq!(sat(x), sat(1.0), "saturating {}", x);
+            assert_eq!(sat(-x), sat(-1.0), "saturating {}", -x);
+        }
+    }
+}
+
+#[test]
+fn saturation_rises_across_domain() {
+    for (sat, _) in SATURATION {
+        let mut last = -1.0;
+        for i in -1000..=1000 {
+            let x = i as f32 / 1000.0;
+            let y = sat(x);
+            assert!(y >= last && y.abs() <= 1.0 && y * x >= 0.0, "saturating {} gives {}", x, y);
+            last = y;
+        }
+    }
+}
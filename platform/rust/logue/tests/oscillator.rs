@@ -0,0 +1,108 @@
+//! The callbacks `declare_oscillator!` generates, called through the
+//! hook table as the firmware does.
+
+use std::sync::{Mutex, MutexGuard};
+
+use logue::platform::*;
+use logue::userosc::*;
+use logue::userprg::*;
+
+/// An oscillator that renders a constant, and records the parameter
+/// changes it gets in `PARAMS`.
+struct Constant;
+
+impl Constant {
+    const fn new() -> Self {
+        Constant
+    }
+}
+
+const LEVEL: i32 = 1 << 20;
+
+static PARAMS: Mutex<Vec<(UserOscParamId, u16)>> = Mutex::new(Vec::new());
+
+impl Oscillator for Constant {
+    fn init(&mut self, _platform: u32, _api: u32) {}
+
+    fn cycle(&mut self, _params: &UserOscParams, yn: &mut [i32]) {
+        yn.iter_mut().for_each(|y| *y = LEVEL);
+    }
+
+    fn param(&mut self, index: UserOscParamId, value: u16) {
+        PARAMS.lock().unwrap().push((index, value));
+    }
+}
+
+logue::declare_oscillator!(Constant);
+
+extern "C" {
+    static s_hook_table: UserOscHookTable;
+}
+
+/// The unit is a single static instance, so tests take turns with it.
+fn unit() -> MutexGuard<'static, ()> {
+    static UNIT: Mutex<()> = Mutex::new(());
+    UNIT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn init(platform: u32, api: u32) {
+    unsafe { (s_hook_table.func_entry)(platform, api) }
+}
+
+fn init_compat() {
+    init(USER_TARGET_PLATFORM | K_USER_MODULE_OSC, USER_API_VERSION);
+}
+
+fn cycle() -> Vec<i32> {
+    let params = UserOscParams { shape_lfo: 0, pitch: 60 << 8, cutoff: 0, resonance: 0, reserved0: [0; 3] };
+    let mut yn = vec![-1; 64];
+    unsafe { (s_hook_table.func_cycle)(&params, yn.as_mut_ptr(), yn.len() as u32) }
+    yn
+}
+
+fn param(index: u16, value: u16) -> Vec<(UserOscParamId, u16)> {
+    PARAMS.lock().unwrap().clear();
+    unsafe { (s_hook_table.func_param)(index, value) }
+    PARAMS.lock().unwrap().clone()
+}
+
+#[test]
+fn renders_on_compatible_runtime() {
+    let _unit = unit();
+    init_compat();
+    assert!(cycle().iter().all(|&y| y == LEVEL));
+}
+
+#[test]
+fn silent_on_incompatible_runtime() {
+    let _unit = unit();
+    let newer = ApiVersion::new(ApiVersion(USER_API_VERSION).major() + 1, 0, 0).0;
+    for (platform, api) in [
+        (USER_TARGET_PLATFORM | K_USER_MODULE_OSC, newer),
+        (USER_TARGET_PLATFORM | K_USER_MODULE_REVFX, USER_API_VERSION),
+        (K_USER_MODULE_OSC, USER_API_VERSION),
+    ] {
+        init_compat();
+        init(platform, api);
+        assert!(cycle().iter().all(|&y| y == 0), "rendered after init({:#x}, {:#x})", platform, api);
+        assert_eq!(param(0, 1), [], "changed a parameter after init({:#x}, {:#x})", platform, api);
+    }
+}
+
+#[test]
+fn passes_known_params() {
+    let _unit = unit();
+    init_compat();
+    assert_eq!(param(0, 100), [(UserOscParamId::Id1, 100)]);
+    assert_eq!(param(5, 3), [(UserOscParamId::Id6, 3)]);
+    assert_eq!(param(7, 1023), [(UserOscParamId::ShiftShape, 1023)]);
+}
+
+#[test]
+fn drops_unknown_params() {
+    let _unit = unit();
+    init_compat();
+    for index in [8, 100, 0xffff] {
+        assert_eq!(param(index, 1), [], "passed on parameter index {}", index);
+    }
+}
@@ -0,0 +1,111 @@
+//! Offline rendering of oscillator units on the host, using the `host`
+//! stand-ins of the `logue` crate for the firmware.
+//!
+//! Units are driven the way the firmware drives them: events are
+//! applied between 64-frame blocks, and each block is rendered with one
+//! call to the cycle callback at 48 kHz.
+
+use logue::manifest::Manifest;
+use logue::platform::*;
+use logue::userosc::{Oscillator, UserOscParamId, UserOscParams};
+use logue::userprg::K_USER_MODULE_OSC;
+
+pub mod midi;
+pub mod script;
+pub mod wav;
+
+pub const SAMPLE_RATE: u32 = 48000;
+
+/// Frames rendered by each call to the cycle callback.
+pub const BLOCK_FRAMES: usize = 64;
+
+/// Something that happens to the oscillator.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    /// Set a parameter, as from the edit menu.
+    Param(UserOscParamId, u16),
+    /// Set the pitch and start a note. The pitch is as in
+    /// `UserOscParams`: the note number in the high byte and the fine
+    /// offset in the low byte.
+    NoteOn(u16),
+    /// End the current note.
+    NoteOff,
+    /// Change the pitch without starting a note.
+    Pitch(u16),
+    /// Set the value of the LFO applied to the shape parameter, as a
+    /// Q31 fraction.
+    ShapeLfo(i32),
+    /// Stop rendering.
+    End,
+}
+
+/// An event and the frame it happens at.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimedEvent {
+    pub frame: u64,
+    pub event: Event,
+}
+
+/// Create an oscillator by name.
+pub fn unit(name: &str) -> Option<Box<dyn Oscillator>> {
+    match name {
+        "raves" => Some(Box::new(raves::Raves::new())),
+        _ => None,
+    }
+}
+
+/// The manifest of an oscillator, by name.
+pub fn manifest(name: &str) -> Option<&'static Manifest> {
+    match name {
+        "raves" => Some(&raves::manifest::MANIFEST),
+        _ => None,
+    }
+}
+
+/// Names of the units `unit` knows about.
+pub const UNITS: [&str; 1] = ["raves"];
+
+/// Initialize `osc`, then render it while applying `events` until the
+/// first `End` event, or until the block after the last event. Events
+/// take effect at the start of the block containing their frame.
+pub fn render(osc: &mut dyn Oscillator, events: &[TimedEvent]) -> Vec<i32> {
+    let mut events = events.to_vec();
+    events.sort_by_key(|e| e.frame);
+    let end = match events.iter().find(|e| e.event == Event::End) {
+        Some(e) => e.frame,
+        None => events.last().map_or(0, |e| (e.frame / BLOCK_FRAMES as u64 + 1) * BLOCK_FRAMES as u64),
+    };
+
+    let mut params = UserOscParams {
+        shape_lfo: 0,
+        pitch: 60 << 8,
+        cutoff: 0,
+        resonance: 0,
+        reserved0: [0; 3],
+    };
+    osc.init(USER_TARGET_PLATFORM | K_USER_MODULE_OSC, USER_API_VERSION);
+
+    let mut out = Vec::new();
+    let mut pending = events.iter().peekable();
+    while (out.len() as u64) < end {
+        let block_end = (out.len() + BLOCK_FRAMES) as u64;
+        while let Some(e) = pending.next_if(|e| e.frame < block_end) {
+            match e.event {
+                Event::Param(id, value) => osc.param(id, value),
+                Event::NoteOn(pitch) => {
+                    params.pitch = pitch;
+                    osc.note_on(&params);
+                }
+                Event::NoteOff => osc.note_off(&params),
+                Event::Pitch(pitch) => params.pitch = pitch,
+                Event::ShapeLfo(lfo) => params.shape_lfo = lfo,
+                Event::End => {}
+            }
+        }
+        let start = out.len();
+        out.resize(start + BLOCK_FRAMES, 0);
+        osc.cycle(&params, &mut out[start..]);
+    }
+    out.truncate(end as usize);
+    out
+}
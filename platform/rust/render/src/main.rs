@@ -0,0 +1,94 @@
+//! Render an oscillator unit to a WAV file.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::process;
+
+use logue_render::*;
+
+const USAGE: &str = "\
+usage: logue-render [--unit <name>] [--seed <n>] [MIDI options] <input> <output.wav>
+
+Renders the events in <input> through an oscillator, by default raves,
+and writes the result as a 48 kHz 32-bit WAV file. The input is either a
+script (see the script module for the format) or, if its name ends in
+.mid or .midi, a Standard MIDI File.
+
+MIDI options:
+    --channel <1-16>         only listen to one channel
+    --cc <param>=<cc>        set a parameter (1-6, shape, shiftshape)
+                             with a controller (default: 1-6 on 20-25,
+                             shape on 54, shiftshape on 55)
+    --lfo-cc <cc|none>       controller for the shape LFO (default: 1)
+    --bend-range <semitones> pitch bend range (default: 2)
+    --tail <seconds>         time rendered after the last event (default: 1)
+";
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+fn run(args: &[String]) -> Result<()> {
+    let mut unit_name = "raves".to_string();
+    let mut seed = 1;
+    let mut map = midi::MidiMap::default();
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--unit" => unit_name = args.next().ok_or("--unit needs a value")?.clone(),
+            "--seed" => seed = args.next().ok_or("--seed needs a value")?.parse()?,
+            "--channel" => {
+                let channel: u8 = args.next().ok_or("--channel needs a value")?.parse()?;
+                if !(1..=16).contains(&channel) {
+                    return Err("channels are numbered 1 to 16".into());
+                }
+                map.channel = Some(channel - 1);
+            }
+            "--cc" => {
+                let arg = args.next().ok_or("--cc needs a value")?;
+                let (param, cc) = arg.split_once('=').ok_or("--cc takes <param>=<cc>")?;
+                let id = script::param_id(param).ok_or_else(|| format!("unknown parameter {}", param))?;
+                map.params.retain(|&(_, p)| p != id);
+                map.params.push((cc.parse()?, id));
+            }
+            "--lfo-cc" => {
+                map.lfo_cc = match args.next().ok_or("--lfo-cc needs a value")?.as_str() {
+                    "none" => None,
+                    cc => Some(cc.parse()?),
+                }
+            }
+            "--bend-range" => map.bend_range = args.next().ok_or("--bend-range needs a value")?.parse()?,
+            "--tail" => map.tail = args.next().ok_or("--tail needs a value")?.parse()?,
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg).into()),
+            _ => files.push(arg),
+        }
+    }
+    let (input, output) = match files.as_slice() {
+        [input, output] => (input, output),
+        _ => {
+            eprint!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    let mut osc = unit(&unit_name)
+        .ok_or_else(|| format!("unknown unit {} (known: {})", unit_name, UNITS.join(", ")))?;
+    let events = if input.ends_with(".mid") || input.ends_with(".midi") {
+        midi::parse(&fs::read(input)?, &map, manifest(&unit_name).unwrap())?
+    } else {
+        script::parse(&fs::read_to_string(input)?)?
+    };
+    logue::host::osc_rand_seed(seed);
+    let samples = render(osc.as_mut(), &events);
+    wav::write_q31(&mut BufWriter::new(File::create(output)?), &samples)?;
+    println!("wrote {} ({:.2} s)", output, samples.len() as f64 / SAMPLE_RATE as f64);
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
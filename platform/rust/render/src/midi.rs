@@ -0,0 +1,170 @@
+//! Driving oscillators from Standard MIDI Files.
+//!
+//! Units are monophonic, so notes are played with last-note priority:
+//! releasing the sounding note returns to the most recent note still
+//! held, and only releasing the last one ends the note. Pitch bend
+//! offsets the pitch by up to `MidiMap::bend_range` semitones.
+//!
+//! Controllers set unit parameters, scaled from 0-127 to the range the
+//! unit's manifest declares for them (or to the 10-bit range of shape
+//! and shift-shape), and one controller can be used as the shape LFO.
+
+use std::error::Error;
+
+use logue::manifest::Manifest;
+use logue::userosc::UserOscParamId;
+use midly::{MetaMessage, MidiMessage, Smf, Timing, TrackEventKind};
+
+use crate::{Event, TimedEvent, SAMPLE_RATE};
+
+/// How MIDI messages map to events.
+#[derive(Clone, Debug)]
+pub struct MidiMap {
+    /// Channel to listen to (0-15), or all of them.
+    pub channel: Option<u8>,
+    /// Controller numbers setting each parameter.
+    pub params: Vec<(u8, UserOscParamId)>,
+    /// Controller setting the shape LFO, from 0.0 at 0 to 1.0 at 127.
+    pub lfo_cc: Option<u8>,
+    /// Pitch bend range, in semitones.
+    pub bend_range: f32,
+    /// Time to keep rendering after the last event, in seconds.
+    pub tail: f32,
+}
+
+impl Default for MidiMap {
+    /// Parameters 1 to 6 on controllers 20 to 25, shape and shift-shape
+    /// on 54 and 55 as on the NTS-1, and the modulation wheel as the
+    /// shape LFO.
+    fn default() -> Self {
+        MidiMap {
+            channel: None,
+            params: vec![
+                (20, UserOscParamId::Id1),
+                (21, UserOscParamId::Id2),
+                (22, UserOscParamId::Id3),
+                (23, UserOscParamId::Id4),
+                (24, UserOscParamId::Id5),
+                (25, UserOscParamId::Id6),
+                (54, UserOscParamId::Shape),
+                (55, UserOscParamId::ShiftShape),
+            ],
+            lfo_cc: Some(1),
+            bend_range: 2.0,
+            tail: 1.0,
+        }
+    }
+}
+
+/// Value of parameter `id` for a controller value.
+fn param_value(manifest: &Manifest, id: UserOscParamId, cc: u8) -> u16 {
+    let span = match id {
+        UserOscParamId::Shape | UserOscParamId::ShiftShape => 1023,
+        UserOscParamId::Id1 => manifest_span(manifest, 0),
+        UserOscParamId::Id2 => manifest_span(manifest, 1),
+        UserOscParamId::Id3 => manifest_span(manifest, 2),
+        UserOscParamId::Id4 => manifest_span(manifest, 3),
+        UserOscParamId::Id5 => manifest_span(manifest, 4),
+        UserOscParamId::Id6 => manifest_span(manifest, 5),
+    };
+    ((cc as u32 * span + 63) / 127) as u16
+}
+
+/// Width of the range of a parameter, which the firmware sends as
+/// values starting at 0.
+fn manifest_span(manifest: &Manifest, index: usize) -> u32 {
+    manifest.params.get(index).map_or(0, |p| (p.max - p.min) as u32)
+}
+
+/// Pitch as in `UserOscParams` for a note bent by `semitones`.
+fn pitch(note: u8, semitones: f32) -> u16 {
+    (note as f32 * 256.0 + semitones * 256.0).round().clamp(0.0, 0xFFFF as f32) as u16
+}
+
+/// Convert the contents of a MIDI file to events for an oscillator
+/// described by `manifest`.
+pub fn parse(data: &[u8], map: &MidiMap, manifest: &Manifest) -> Result<Vec<TimedEvent>, Box<dyn Error>> {
+    let smf = Smf::parse(data)?;
+
+    // Merge the tracks, keeping events of the same tick in track order.
+    let mut messages = Vec::new();
+    for track in &smf.tracks {
+        let mut tick = 0u64;
+        for event in track {
+            tick += event.delta.as_int() as u64;
+            messages.push((tick, event.kind));
+        }
+    }
+    messages.sort_by_key(|(tick, _)| *tick);
+
+    let mut events = Vec::new();
+    let mut held: Vec<u8> = Vec::new();
+    let mut bend = 0.0;
+    // Time of the last tempo change, in ticks and seconds.
+    let mut tempo_tick = 0;
+    let mut tempo_secs = 0.0;
+    let mut secs_per_tick = match smf.header.timing {
+        Timing::Metrical(tpb) => 0.5 / tpb.as_int() as f64,
+        Timing::Timecode(fps, sub) => 1.0 / (fps.as_f32() as f64 * sub as f64),
+    };
+    let mut last_frame = 0;
+    for (tick, kind) in messages {
+        let secs = tempo_secs + (tick - tempo_tick) as f64 * secs_per_tick;
+        let frame = (secs * SAMPLE_RATE as f64).round() as u64;
+        let mut push = |event| events.push(TimedEvent { frame, event });
+        let (channel, message) = match kind {
+            TrackEventKind::Meta(MetaMessage::Tempo(us_per_beat)) => {
+                if let Timing::Metrical(tpb) = smf.header.timing {
+                    tempo_tick = tick;
+                    tempo_secs = secs;
+                    secs_per_tick = us_per_beat.as_int() as f64 * 1e-6 / tpb.as_int() as f64;
+                }
+                continue;
+            }
+            TrackEventKind::Midi { channel, message } => (channel.as_int(), message),
+            _ => continue,
+        };
+        if map.channel.is_some_and(|c| c != channel) {
+            continue;
+        }
+        last_frame = frame;
+        match message {
+            MidiMessage::NoteOn { key, vel } if vel > 0 => {
+                let key = key.as_int();
+                held.retain(|&k| k != key);
+                held.push(key);
+                push(Event::NoteOn(pitch(key, bend)));
+            }
+            MidiMessage::NoteOn { key, .. } | MidiMessage::NoteOff { key, .. } => {
+                let key = key.as_int();
+                let sounding = held.last() == Some(&key);
+                held.retain(|&k| k != key);
+                match (sounding, held.last()) {
+                    (true, Some(&prev)) => push(Event::Pitch(pitch(prev, bend))),
+                    (true, None) => push(Event::NoteOff),
+                    (false, _) => {}
+                }
+            }
+            MidiMessage::PitchBend { bend: b } => {
+                bend = b.as_f32() * map.bend_range;
+                if let Some(&key) = held.last() {
+                    push(Event::Pitch(pitch(key, bend)));
+                }
+            }
+            MidiMessage::Controller { controller, value } => {
+                let (cc, value) = (controller.as_int(), value.as_int());
+                if map.lfo_cc == Some(cc) {
+                    push(Event::ShapeLfo((value as f64 / 127.0 * i32::MAX as f64) as i32));
+                }
+                for &(_, id) in map.params.iter().filter(|(c, _)| *c == cc) {
+                    push(Event::Param(id, param_value(manifest, id, value)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let end = last_frame + (map.tail as f64 * SAMPLE_RATE as f64) as u64;
+    events.push(TimedEvent { frame: end, event: Event::End });
+    Ok(events)
+}
@@ -0,0 +1,105 @@
+//! Event scripts: plain text files listing what happens to an
+//! oscillator and when. Each line holds a time in seconds, an event and
+//! its arguments; `#` starts a comment:
+//!
+//! ```text
+//! 0.0  param 1 5        # parameters 1 to 6, shape or shiftshape
+//! 0.0  param shape 512
+//! 0.0  noteon 60        # note number, optionally followed by fine (0-255)
+//! 0.5  lfo -0.25        # shape LFO value in [-1.0, 1.0]
+//! 1.0  pitch 62 128     # change pitch without a new note
+//! 1.5  noteoff
+//! 2.0  end
+//! ```
+
+use std::convert::TryFrom;
+use std::fmt;
+
+use logue::userosc::UserOscParamId;
+
+use crate::{Event, TimedEvent, SAMPLE_RATE};
+
+/// A line of a script that could not be parsed.
+#[derive(Debug)]
+pub struct ScriptError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// The latest time, in seconds, an event can be scheduled at: an hour,
+/// far longer than any render but still a representable frame.
+pub const MAX_TIME: f64 = 3600.0;
+
+/// Parse a parameter name as used in scripts: `1` to `6`, `shape` or
+/// `shiftshape`.
+pub fn param_id(name: &str) -> Option<UserOscParamId> {
+    match name {
+        "shape" => Some(UserOscParamId::Shape),
+        "shiftshape" => Some(UserOscParamId::ShiftShape),
+        _ => match name.parse::<u16>() {
+            Ok(n @ 1..=6) => UserOscParamId::try_from(n - 1).ok(),
+            _ => None,
+        },
+    }
+}
+
+/// Convert a shape LFO value in [-1.0, 1.0] to Q31, saturating.
+pub fn lfo_to_q31(value: f32) -> i32 {
+    (value.clamp(-1.0, 1.0) as f64 * i32::MAX as f64) as i32
+}
+
+pub fn parse(src: &str) -> Result<Vec<TimedEvent>, ScriptError> {
+    let mut events = Vec::new();
+    for (i, line) in src.lines().enumerate() {
+        let err = |message: String| ScriptError { line: i + 1, message };
+        let line = line.split('#').next().unwrap();
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (time, event, args) = match words.as_slice() {
+            [] => continue,
+            [_] => return Err(err("missing event".into())),
+            [time, event, args @ ..] => (time, *event, args),
+        };
+        let seconds: f64 = time.parse()
+            .ok()
+            .filter(|t: &f64| t.is_finite() && *t >= 0.0)
+            .ok_or_else(|| err(format!("bad time \"{}\"", time)))?;
+        if seconds > MAX_TIME {
+            return Err(err(format!("time \"{}\" is past the limit of {} s", time, MAX_TIME)));
+        }
+
+        let number = |s: &str| s.parse::<u16>().map_err(|_| err(format!("bad number \"{}\"", s)));
+        let pitch = |args: &[&str]| -> Result<u16, ScriptError> {
+            match args {
+                [note] => Ok(number(note)?.min(0xFF) << 8),
+                [note, fine] => Ok(number(note)?.min(0xFF) << 8 | number(fine)?.min(0xFF)),
+                _ => Err(err(format!("{} takes a note and an optional fine offset", event))),
+            }
+        };
+        let event = match (event, args) {
+            ("param", [id, value]) => {
+                let id = param_id(id).ok_or_else(|| err(format!("unknown parameter \"{}\"", id)))?;
+                Event::Param(id, number(value)?)
+            }
+            ("noteon", args) => Event::NoteOn(pitch(args)?),
+            ("pitch", args) => Event::Pitch(pitch(args)?),
+            ("noteoff", []) => Event::NoteOff,
+            ("lfo", [value]) => {
+                let value: f32 = value.parse().map_err(|_| err(format!("bad LFO value \"{}\"", value)))?;
+                Event::ShapeLfo(lfo_to_q31(value))
+            }
+            ("end", []) => Event::End,
+            _ => return Err(err(format!("unknown event or wrong arguments: {}", line.trim()))),
+        };
+        let frame = (seconds * SAMPLE_RATE as f64).round() as u64;
+        events.push(TimedEvent { frame, event });
+    }
+    Ok(events)
+}
@@ -0,0 +1,30 @@
+//! Writing rendered output as WAV files.
+
+use std::io::{self, Write};
+
+use crate::SAMPLE_RATE;
+
+/// Write mono Q31 samples as a 32-bit PCM WAV file, which represents
+/// them exactly.
+pub fn write_q31<W: Write>(w: &mut W, samples: &[i32]) -> io::Result<()> {
+    let data_len = samples.len() as u32 * 4;
+    w.write_all(b"RIFF")?;
+    w.write_all(&(36 + data_len).to_le_bytes())?;
+    w.write_all(b"WAVE")?;
+
+    w.write_all(b"fmt ")?;
+    w.write_all(&16u32.to_le_bytes())?;
+    w.write_all(&1u16.to_le_bytes())?; // PCM
+    w.write_all(&1u16.to_le_bytes())?; // mono
+    w.write_all(&SAMPLE_RATE.to_le_bytes())?;
+    w.write_all(&(SAMPLE_RATE * 4).to_le_bytes())?; // bytes per second
+    w.write_all(&4u16.to_le_bytes())?; // bytes per frame
+    w.write_all(&32u16.to_le_bytes())?; // bits per sample
+
+    w.write_all(b"data")?;
+    w.write_all(&data_len.to_le_bytes())?;
+    for s in samples {
+        w.write_all(&s.to_le_bytes())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,70 @@
+//! Check the mapping from MIDI messages to oscillator events.
+
+use logue::userosc::UserOscParamId;
+use logue_render::midi::{self, MidiMap};
+use logue_render::{Event, TimedEvent};
+
+fn vlq(mut n: u32) -> Vec<u8> {
+    let mut bytes = vec![(n & 0x7F) as u8];
+    n >>= 7;
+    while n > 0 {
+        bytes.insert(0, 0x80 | (n & 0x7F) as u8);
+        n >>= 7;
+    }
+    bytes
+}
+
+/// A single-track file at 480 ticks per beat.
+fn smf(events: &[(u32, &[u8])]) -> Vec<u8> {
+    let mut track = Vec::new();
+    for (delta, message) in events {
+        track.extend(vlq(*delta));
+        track.extend_from_slice(message);
+    }
+    track.extend_from_slice(&[0x00, 0xFF, 0x2F, 0x00]);
+    let mut data = b"MThd\0\0\0\x06\0\0\0\x01\x01\xE0MTrk".to_vec();
+    data.extend_from_slice(&(track.len() as u32).to_be_bytes());
+    data.extend(track);
+    data
+}
+
+#[test]
+fn maps_notes_bend_and_controllers() {
+    // 120 BPM, so 240 ticks are a quarter of a second (12000 frames).
+    let data = smf(&[
+        (0, &[0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20]),
+        (0, &[0xB0, 20, 64]),        // parameter 1
+        (0, &[0x90, 60, 100]),       // note on
+        (240, &[0xE0, 0x00, 0x60]),  // bend up a semitone
+        (240, &[0x90, 64, 100]),     // second note on
+        (240, &[0xB0, 1, 127]),      // shape LFO
+        (240, &[0x80, 64, 0]),       // back to the first note
+        (240, &[0x90, 60, 0]),       // note off, as a note on
+    ]);
+    let events = midi::parse(&data, &MidiMap::default(), &raves::manifest::MANIFEST).unwrap();
+    let expected = [
+        (0, Event::Param(UserOscParamId::Id1, 23)),
+        (0, Event::NoteOn(60 << 8)),
+        (12000, Event::Pitch(61 << 8)),
+        (24000, Event::NoteOn(65 << 8)),
+        (36000, Event::ShapeLfo(i32::MAX)),
+        (48000, Event::Pitch(61 << 8)),
+        (60000, Event::NoteOff),
+        (108000, Event::End),
+    ];
+    let expected: Vec<TimedEvent> = expected.iter()
+        .map(|&(frame, event)| TimedEvent { frame, event })
+        .collect();
+    assert_eq!(events, expected);
+}
+
+#[test]
+fn filters_channels() {
+    let data = smf(&[(0, &[0x91, 60, 100]), (0, &[0x90, 62, 100])]);
+    let map = MidiMap { channel: Some(1), tail: 0.0, ..MidiMap::default() };
+    let events = midi::parse(&data, &map, &raves::manifest::MANIFEST).unwrap();
+    assert_eq!(events, [
+        TimedEvent { frame: 0, event: Event::NoteOn(60 << 8) },
+        TimedEvent { frame: 0, event: Event::End },
+    ]);
+}
@@ -0,0 +1,134 @@
+//! Check when `render` applies events and where it stops, and the WAV
+//! files it writes.
+
+use logue::platform::*;
+use logue::userosc::{Oscillator, UserOscParamId, UserOscParams};
+use logue::userprg::K_USER_MODULE_OSC;
+use logue_render::{render, wav, Event, TimedEvent, BLOCK_FRAMES, SAMPLE_RATE};
+
+/// What happened to a `Recorder`, in order.
+#[derive(Debug, PartialEq)]
+enum Call {
+    Init(u32, u32),
+    Param(UserOscParamId, u16),
+    On(u16),
+    Off,
+    /// A block, with the pitch and shape LFO it was rendered with.
+    Cycle(u16, i32),
+}
+
+/// An oscillator that records its calls and renders the number of the
+/// block.
+#[derive(Default)]
+struct Recorder {
+    calls: Vec<Call>,
+    blocks: i32,
+}
+
+impl Oscillator for Recorder {
+    fn init(&mut self, platform: u32, api: u32) {
+        self.calls.push(Call::Init(platform, api));
+    }
+
+    fn cycle(&mut self, params: &UserOscParams, yn: &mut [i32]) {
+        assert_eq!(yn.len(), BLOCK_FRAMES);
+        self.calls.push(Call::Cycle(params.pitch, params.shape_lfo));
+        yn.iter_mut().for_each(|y| *y = self.blocks);
+        self.blocks += 1;
+    }
+
+    fn note_on(&mut self, params: &UserOscParams) {
+        self.calls.push(Call::On(params.pitch));
+    }
+
+    fn note_off(&mut self, _params: &UserOscParams) {
+        self.calls.push(Call::Off);
+    }
+
+    fn param(&mut self, index: UserOscParamId, value: u16) {
+        self.calls.push(Call::Param(index, value));
+    }
+}
+
+fn timed(events: &[(u64, Event)]) -> Vec<TimedEvent> {
+    events.iter().map(|&(frame, event)| TimedEvent { frame, event }).collect()
+}
+
+#[test]
+fn events_apply_at_block_starts() {
+    let mut osc = Recorder::default();
+    // Out of order, as `render` sorts them.
+    let out = render(&mut osc, &timed(&[
+        (64, Event::Pitch(62 << 8)),
+        (0, Event::Param(UserOscParamId::Shape, 512)),
+        (10, Event::NoteOn(60 << 8)),
+        (127, Event::ShapeLfo(-1)),
+        (128, Event::NoteOff),
+    ]));
+    assert_eq!(osc.calls, [
+        Call::Init(USER_TARGET_PLATFORM | K_USER_MODULE_OSC, USER_API_VERSION),
+        Call::Param(UserOscParamId::Shape, 512),
+        Call::On(60 << 8),
+        Call::Cycle(60 << 8, 0),
+        Call::Cycle(62 << 8, -1),
+        Call::Off,
+        Call::Cycle(62 << 8, -1),
+    ]);
+    // Up to the end of the block after the last event.
+    assert_eq!(out.len(), 3 * BLOCK_FRAMES);
+    assert_eq!(out[BLOCK_FRAMES - 1..BLOCK_FRAMES + 1], [0, 1]);
+}
+
+#[test]
+fn stops_at_end() {
+    let mut osc = Recorder::default();
+    let out = render(&mut osc, &timed(&[
+        (0, Event::NoteOn(60 << 8)),
+        (150, Event::End),
+        (140, Event::NoteOff),
+        (200, Event::NoteOn(64 << 8)),
+        (300, Event::End),
+    ]));
+    // Mid-block, after rendering the whole of the block.
+    assert_eq!(out.len(), 150);
+    assert_eq!(out[149], 2);
+    assert_eq!(osc.calls.iter().filter(|c| matches!(c, Call::Cycle(..))).count(), 3);
+    assert_eq!(osc.calls.last(), Some(&Call::Cycle(60 << 8, 0)));
+    assert!(!osc.calls.contains(&Call::On(64 << 8)));
+
+    let mut osc = Recorder::default();
+    assert_eq!(render(&mut osc, &timed(&[(0, Event::End)])), []);
+    assert_eq!(osc.calls.len(), 1);
+    assert_eq!(render(&mut Recorder::default(), &[]), []);
+}
+
+#[test]
+fn wav_header() {
+    let samples = [0, i32::MAX, i32::MIN, -1, 0x0102_0304];
+    let mut wav = Vec::new();
+    wav::write_q31(&mut wav, &samples).unwrap();
+    assert_eq!(wav.len(), 44 + 4 * samples.len());
+
+    let u16_at = |i: usize| u16::from_le_bytes([wav[i], wav[i + 1]]);
+    let u32_at = |i: usize| u32::from_le_bytes([wav[i], wav[i + 1], wav[i + 2], wav[i + 3]]);
+    assert_eq!(&wav[0..4], b"RIFF");
+    // The length of everything after the RIFF length.
+    assert_eq!(u32_at(4) as usize, wav.len() - 8);
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    assert_eq!(u32_at(16), 16);
+    assert_eq!((u16_at(20), u16_at(22)), (1, 1));
+    assert_eq!(u32_at(24), SAMPLE_RATE);
+    // The byte rate is the sample rate times the bytes per frame.
+    assert_eq!(u32_at(28), 48000 * 4);
+    assert_eq!((u16_at(32), u16_at(34)), (4, 32));
+    assert_eq!(&wav[36..40], b"data");
+    assert_eq!(u32_at(40) as usize, 4 * samples.len());
+    assert_eq!(wav[44..48], [0, 0, 0, 0]);
+    assert_eq!(wav[48..52], [0xff, 0xff, 0xff, 0x7f]);
+    assert_eq!(wav[60..64], [4, 3, 2, 1]);
+
+    let mut empty = Vec::new();
+    wav::write_q31(&mut empty, &[]).unwrap();
+    assert_eq!(empty.len(), 44);
+    assert_eq!(empty[4..8], 36u32.to_le_bytes());
+}
@@ -0,0 +1,95 @@
+//! Check the parsing of event scripts.
+
+use logue::userosc::UserOscParamId;
+use logue_render::script::{self, lfo_to_q31, param_id};
+use logue_render::{Event, TimedEvent};
+
+fn parse(src: &str) -> Vec<(u64, Event)> {
+    script::parse(src).unwrap().into_iter().map(|TimedEvent { frame, event }| (frame, event)).collect()
+}
+
+/// The line and message of the error a script fails with.
+fn error(src: &str) -> (usize, String) {
+    let e = script::parse(src).unwrap_err();
+    (e.line, e.message)
+}
+
+#[test]
+fn events() {
+    let src = "\
+# a comment
+0.0  param 1 5
+0    param shape 512   # trailing comment
+
+0.5  noteon 60
+0.25 lfo -0.5
+1.0  pitch 62 128
+1.5  noteoff
+2    end
+";
+    assert_eq!(parse(src), [
+        (0, Event::Param(UserOscParamId::Id1, 5)),
+        (0, Event::Param(UserOscParamId::Shape, 512)),
+        (24000, Event::NoteOn(60 << 8)),
+        (12000, Event::ShapeLfo(lfo_to_q31(-0.5))),
+        (48000, Event::Pitch(62 << 8 | 128)),
+        (72000, Event::NoteOff),
+        (96000, Event::End),
+    ]);
+    // Times are rounded to the nearest frame.
+    assert_eq!(parse("0.00001 noteoff"), [(0, Event::NoteOff)]);
+    assert_eq!(parse("0.00002 noteoff"), [(1, Event::NoteOff)]);
+}
+
+#[test]
+fn params() {
+    assert_eq!(param_id("1"), Some(UserOscParamId::Id1));
+    assert_eq!(param_id("6"), Some(UserOscParamId::Id6));
+    assert_eq!(param_id("shape"), Some(UserOscParamId::Shape));
+    assert_eq!(param_id("shiftshape"), Some(UserOscParamId::ShiftShape));
+    for bad in ["0", "7", "-1", "Shape", ""] {
+        assert_eq!(param_id(bad), None, "{:?}", bad);
+    }
+}
+
+#[test]
+fn pitch_and_fine() {
+    assert_eq!(parse("0 noteon 69"), [(0, Event::NoteOn(69 << 8))]);
+    assert_eq!(parse("0 noteon 69 255"), [(0, Event::NoteOn(69 << 8 | 255))]);
+    // Notes and fine offsets beyond a byte saturate.
+    assert_eq!(parse("0 pitch 300 256"), [(0, Event::Pitch(0xFFFF))]);
+    assert_eq!(parse("0 pitch 0 1000"), [(0, Event::Pitch(0xFF))]);
+}
+
+#[test]
+fn lfo_clamps() {
+    assert_eq!(lfo_to_q31(0.0), 0);
+    assert_eq!(lfo_to_q31(1.0), i32::MAX);
+    assert_eq!(lfo_to_q31(-1.0), -i32::MAX);
+    assert_eq!(lfo_to_q31(0.5), i32::MAX / 2);
+    assert_eq!(lfo_to_q31(2.0), i32::MAX);
+    assert_eq!(lfo_to_q31(-1e9), -i32::MAX);
+    assert_eq!(parse("0 lfo 1.5\n0 lfo -7"), [(0, Event::ShapeLfo(i32::MAX)), (0, Event::ShapeLfo(-i32::MAX))]);
+}
+
+#[test]
+fn errors() {
+    let message = |s: &str| s.to_string();
+    assert_eq!(error("0 noteoff\n1"), (2, message("missing event")));
+    assert_eq!(error("-1 noteoff"), (1, message("bad time \"-1\"")));
+    assert_eq!(error("soon noteoff"), (1, message("bad time \"soon\"")));
+    assert_eq!(error("inf noteoff"), (1, message("bad time \"inf\"")));
+    assert_eq!(error("infinity noteoff"), (1, message("bad time \"infinity\"")));
+    assert_eq!(error("NaN noteoff"), (1, message("bad time \"NaN\"")));
+    assert_eq!(error("3600.5 noteoff"), (1, message("time \"3600.5\" is past the limit of 3600 s")));
+    assert_eq!(error("1e300 noteoff"), (1, message("time \"1e300\" is past the limit of 3600 s")));
+    assert_eq!(error("0 param 7 5"), (1, message("unknown parameter \"7\"")));
+    assert_eq!(error("0 param 1 -5"), (1, message("bad number \"-5\"")));
+    assert_eq!(error("0 param 1 70000"), (1, message("bad number \"70000\"")));
+    assert_eq!(error("0 noteon"), (1, message("noteon takes a note and an optional fine offset")));
+    assert_eq!(error("0 pitch 60 0 0"), (1, message("pitch takes a note and an optional fine offset")));
+    assert_eq!(error("0 noteon C4"), (1, message("bad number \"C4\"")));
+    assert_eq!(error("0 lfo up"), (1, message("bad LFO value \"up\"")));
+    assert_eq!(error("\n\n0 noteoff 1 # note"), (3, message("unknown event or wrong arguments: 0 noteoff 1")));
+    assert_eq!(error("0 bend 2"), (1, message("unknown event or wrong arguments: 0 bend 2")));
+}
@@ -0,0 +1,34 @@
+//! Conversion of linked units to the raw images the synthesizers load,
+//! as `objcopy -O binary` does.
+
+use object::elf;
+use object::read::elf::{ElfFile32, ProgramHeader};
+use object::Endianness;
+
+use crate::Result;
+
+/// Flatten the loadable contents of an ELF file into an image starting
+/// at its lowest load address. Gaps between segments are zero-filled,
+/// and memory the firmware initializes (`.bss`) is left out.
+pub fn elf_to_binary(data: &[u8]) -> Result<Vec<u8>> {
+    let file = ElfFile32::<Endianness>::parse(data)?;
+    let endian = file.endian();
+    let mut segments = Vec::new();
+    for ph in file.elf_program_headers() {
+        if ph.p_type(endian) != elf::PT_LOAD || ph.p_filesz(endian) == 0 {
+            continue;
+        }
+        let contents = ph.data(endian, data).map_err(|_| "segment outside of file")?;
+        segments.push((ph.p_paddr(endian) as usize, contents));
+    }
+    let base = match segments.iter().map(|(addr, _)| *addr).min() {
+        Some(base) => base,
+        None => return Err("no loadable segments".into()),
+    };
+    let end = segments.iter().map(|(addr, c)| addr + c.len()).max().unwrap();
+    let mut image = vec![0; end - base];
+    for (addr, contents) in segments {
+        image[addr - base..addr - base + contents.len()].copy_from_slice(contents);
+    }
+    Ok(image)
+}
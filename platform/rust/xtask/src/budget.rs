@@ -0,0 +1,71 @@
+//! `cargo xtask budget`: estimate how much of the real-time budget a
+//! unit's callbacks use, by playing it on an emulated Cortex-M4.
+//!
+//! The unit is built as for `package`, and random schedules of callbacks
+//! (see `logue::host::sim`) are played to its payload with `logue-emu`,
+//! with every cycle rendering a full 64-frame block. Each callback's
+//! worst case is reported against the cycles in a block, and the task
+//! fails if any takes more than the allowed share.
+
+use logue::host::osc_rand_seed;
+use logue::host::sim::*;
+use logue::platform::USER_API_VERSION;
+use logue::userprg::K_USER_MODULE_OSC;
+use logue_emu::budget::*;
+use logue_emu::Unit;
+
+use crate::package::{build, manifest};
+use crate::Result;
+
+pub fn run(args: &[String]) -> Result<()> {
+    let mut platform = "nutekt-digital".to_string();
+    let mut name = "raves".to_string();
+    let mut clock_mhz = (DEFAULT_CLOCK_HZ / 1_000_000) as f64;
+    let mut max_percent = DEFAULT_MAX_PERCENT;
+    let mut seeds = 10;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |option: &str| args.next().ok_or(format!("{} needs a value", option));
+        match arg.as_str() {
+            "--platform" => platform = value(arg)?.clone(),
+            "--clock" => clock_mhz = value(arg)?.parse().map_err(|_| "--clock needs a number of MHz")?,
+            "--max" => max_percent = value(arg)?.parse().map_err(|_| "--max needs a percentage")?,
+            "--seeds" => seeds = value(arg)?.parse().map_err(|_| "--seeds needs a count")?,
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg).into()),
+            _ => name = arg.clone(),
+        }
+    }
+
+    let manifest = manifest(&name)?;
+    if manifest.module != K_USER_MODULE_OSC {
+        return Err(format!("{} is not an oscillator, the only kind of unit that can be emulated", name).into());
+    }
+    let profile = Profile::from_manifest(manifest);
+    let (_, payload) = build(&name, &platform)?;
+
+    let mut meter = Meter::new(Unit::load(&payload)?);
+    let platform_id = (payload[8] as u32) << 8;
+    for seed in 1..=seeds {
+        osc_rand_seed(seed);
+        let schedule = full_blocks(&Schedule::random(seed, &profile));
+        Simulator::new(platform_id | K_USER_MODULE_OSC, USER_API_VERSION).run(&mut meter, &schedule);
+    }
+
+    let block = block_cycles((clock_mhz * 1e6) as u64);
+    println!("{} cycles per {}-frame block at {} MHz", block, MAX_FRAMES, clock_mhz);
+    println!("{:<8} {:>8} {:>10} {:>10} {:>8}", "hook", "calls", "mean", "worst", "budget");
+    let mut over = Vec::new();
+    for (hook, name) in HOOKS.iter().zip(["init", "cycle", "on", "off", "mute", "value", "param"]) {
+        let usage = meter.usage(*hook);
+        let percent = 100.0 * usage.worst as f64 / block as f64;
+        println!("{:<8} {:>8} {:>10} {:>10} {:>7.2}%", name, usage.calls, usage.mean(), usage.worst, percent);
+        if is_budgeted(*hook) && percent > max_percent {
+            over.push(format!("{} ({:.2}%)", name, percent));
+        }
+    }
+    if !over.is_empty() {
+        return Err(format!("over {}% of the block budget: {}", max_percent, over.join(", ")).into());
+    }
+    Ok(())
+}
+
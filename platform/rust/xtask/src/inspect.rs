@@ -0,0 +1,325 @@
+//! `cargo xtask inspect`: check a unit file for the problems that stop
+//! the synthesizers from loading it, and describe what it contains.
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use serde_json::Value;
+use zip::ZipArchive;
+
+use logue::manifest::{module_name, Manifest, Param};
+use logue::platform::*;
+use logue::userprg::*;
+
+use crate::unit::{self, layout, HOOK_TABLE_SIZE};
+use crate::Result;
+
+/// Offset of the first callback in a hook table: the magic, API version,
+/// platform and 7 reserved bytes come first.
+const HOOK_CALLBACKS_OFFSET: usize = 16;
+
+pub fn run(args: &[String]) -> Result<()> {
+    let path = match args {
+        [path] => Path::new(path),
+        _ => return Err("usage: cargo xtask inspect <unit file>".into()),
+    };
+    let problems = inspect(path)?;
+    if problems.is_empty() {
+        println!("no problems found");
+        Ok(())
+    } else {
+        for p in &problems {
+            println!("problem: {}", p);
+        }
+        Err(format!("{} problem(s) found in {}", problems.len(), path.display()).into())
+    }
+}
+
+/// Print a report on a unit file, returning the problems found.
+fn inspect(path: &Path) -> Result<Vec<String>> {
+    let mut problems = Vec::new();
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+
+    let names: Vec<String> = archive.file_names().map(String::from).collect();
+    let dir = match names.iter().find_map(|n| n.split('/').next()) {
+        Some(dir) => dir.to_string(),
+        None => return Err("empty archive".into()),
+    };
+    println!("unit directory: {}/", dir);
+    for name in &names {
+        let expected = [format!("{}/", dir), format!("{}/manifest.json", dir),
+                        format!("{}/payload.bin", dir)];
+        if !expected.contains(name) {
+            problems.push(format!("unexpected file in archive: {}", name));
+        }
+    }
+
+    let manifest = read(&mut archive, &format!("{}/manifest.json", dir))?;
+    let payload = read(&mut archive, &format!("{}/payload.bin", dir))?;
+    let manifest: Value = serde_json::from_slice(&manifest)
+        .map_err(|e| format!("manifest.json is not valid JSON: {}", e))?;
+    let header = &manifest["header"];
+
+    // Manifest.
+    let platform = header["platform"].as_str().unwrap_or("");
+    println!("platform: {}", platform);
+    match unit::extension(platform) {
+        Ok(ext) => {
+            if path.extension().and_then(|e| e.to_str()) != Some(ext) {
+                problems.push(format!("{} units must have the .{} extension", platform, ext));
+            }
+        }
+        Err(_) => problems.push(format!("unknown platform \"{}\"", platform)),
+    }
+    let module_str = header["module"].as_str().unwrap_or("");
+    let module = (K_USER_MODULE_MODFX..=K_USER_MODULE_OSC)
+        .find(|&m| module_name(m) == Some(module_str));
+    println!("module: {}", module_str);
+    let api = header["api"].as_str().and_then(parse_version);
+    match api {
+        Some(api) => println!("api: {}.{}-{}", api.major(), api.minor(), api.patch()),
+        None => problems.push("missing or malformed api version".into()),
+    }
+    let version = header["version"].as_str().and_then(parse_version);
+    if version.is_none() {
+        problems.push("missing or malformed unit version".into());
+    }
+    println!("name: {}", header["name"].as_str().unwrap_or(""));
+
+    let params = header["params"].as_array().cloned().unwrap_or_default();
+    let params: Vec<Param> = params.iter().filter_map(|p| {
+        let p = p.as_array()?;
+        Some(Param::new(leak(p.first()?.as_str()?), i32::try_from(p.get(1)?.as_i64()?).ok()?,
+                        i32::try_from(p.get(2)?.as_i64()?).ok()?, leak(p.get(3)?.as_str()?)))
+    }).collect();
+    if params.len() != header["params"].as_array().map_or(0, Vec::len) {
+        problems.push("malformed parameter descriptors".into());
+    }
+    if header["num_param"].as_u64() != Some(params.len() as u64) {
+        problems.push(format!("num_param does not match the {} parameters listed", params.len()));
+    }
+    for p in &params {
+        println!("param: {:<14} {:>4} .. {:<4} {}", format!("\"{}\"", p.name), p.min, p.max, p.unit);
+    }
+    let mut id = |key: &str| match header[key].as_u64().map(u32::try_from) {
+        Some(Ok(id)) => id,
+        _ => {
+            problems.push(format!("missing or malformed {}", key));
+            0
+        }
+    };
+    let checked = Manifest {
+        module: module.unwrap_or(K_USER_MODULE_GLOBAL),
+        dev_id: id("dev_id"),
+        prg_id: id("prg_id"),
+        version: version.unwrap_or(ApiVersion(0)),
+        name: leak(header["name"].as_str().unwrap_or("")),
+        params: Box::leak(params.into_boxed_slice()),
+    };
+    if let Err(e) = checked.validate() {
+        problems.push(format!("manifest: {}", e));
+    }
+
+    // Payload.
+    println!("payload: {} bytes", payload.len());
+    let layout = match module.and_then(layout) {
+        Some(layout) => layout,
+        None => {
+            problems.push("cannot check the payload of an unknown module".into());
+            return Ok(problems);
+        }
+    };
+    if payload.len() > layout.length as usize {
+        problems.push(format!("payload is larger than the {}K {} region", layout.length / 1024, module_str));
+    }
+    if payload.len() < HOOK_TABLE_SIZE {
+        problems.push("payload is too small to hold a hook table".into());
+        return Ok(problems);
+    }
+    let magic = &payload[0..4];
+    println!("hook magic: {}", String::from_utf8_lossy(magic));
+    if magic != layout.magic {
+        problems.push(format!("hook table magic is not {}", String::from_utf8_lossy(layout.magic)));
+    }
+    let payload_api = ApiVersion(u32_at(&payload, 4));
+    println!("hook api: {}.{}-{}", payload_api.major(), payload_api.minor(), payload_api.patch());
+    if let Some(api) = api {
+        if api != payload_api {
+            problems.push("hook table and manifest API versions differ".into());
+        }
+    }
+    if !ApiVersion(USER_API_VERSION).is_compat(payload_api) {
+        problems.push("hook table API version is not supported by the firmware".into());
+    }
+    let payload_platform = payload[8];
+    let platform_name = platform_name(payload_platform);
+    println!("hook platform: {} ({})", payload_platform, platform_name.unwrap_or("unknown"));
+    if platform_name != Some(platform) {
+        problems.push("hook table and manifest platforms differ".into());
+    }
+
+    let text_end = layout.origin + payload.len() as u32;
+    for (i, name) in layout.callbacks.iter().enumerate() {
+        let addr = u32_at(&payload, HOOK_CALLBACKS_OFFSET + 4 * i);
+        println!("hook {:<8} {:#010x}", name, addr);
+        if addr & 1 == 0 {
+            problems.push(format!("{} callback is not Thumb code", name));
+        } else if !(layout.origin..text_end).contains(&(addr & !1)) {
+            problems.push(format!("{} callback is outside the payload ({:#010x}..{:#010x})",
+                                  name, layout.origin, text_end));
+        }
+    }
+    Ok(problems)
+}
+
+fn read(archive: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>> {
+    let mut file = archive.by_name(name).map_err(|_| format!("archive has no {}", name))?;
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Name of the platform with the given byte in hook tables.
+fn platform_name(byte: u8) -> Option<&'static str> {
+    match (byte as u32) << 8 {
+        K_USER_TARGET_PROLOGUE => Some("prologue"),
+        K_USER_TARGET_MINILOGUEXD => Some("minilogue-xd"),
+        K_USER_TARGET_NUTEKTDIGITAL => Some("nutekt-digital"),
+        _ => None,
+    }
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&data[offset..offset + 4]);
+    u32::from_le_bytes(bytes)
+}
+
+/// Parse a version written as in manifests, such as "1.1-0".
+fn parse_version(s: &str) -> Option<ApiVersion> {
+    let (major, rest) = s.split_once('.')?;
+    let (minor, patch) = rest.split_once('-')?;
+    Some(ApiVersion::new(major.parse().ok()?, minor.parse().ok()?, patch.parse().ok()?))
+}
+
+/// `Manifest` borrows its strings for the life of the program, which is
+/// short here.
+fn leak(s: &str) -> &'static str {
+    Box::leak(s.to_string().into_boxed_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    use zip::write::{FileOptions, ZipWriter};
+
+    use super::*;
+    use crate::package::{manifest, manifest_json, write_unit};
+
+    #[test]
+    fn versions() {
+        assert_eq!(parse_version("1.1-0"), Some(ApiVersion::new(1, 1, 0)));
+        assert_eq!(parse_version("12.0-255"), Some(ApiVersion::new(12, 0, 255)));
+        for bad in ["", "1.1", "1-1.0", "1.1-", "1.1-0-0", "a.1-0", "1.256-0", "-1.1-0"] {
+            assert_eq!(parse_version(bad), None, "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn platform_names() {
+        assert_eq!(platform_name(1), Some("prologue"));
+        assert_eq!(platform_name(2), Some("minilogue-xd"));
+        assert_eq!(platform_name(3), Some("nutekt-digital"));
+        assert_eq!(platform_name(0), None);
+        assert_eq!(platform_name(4), None);
+    }
+
+    /// A 256-byte oscillator payload for nutekt-digital, with every
+    /// callback at the same Thumb address.
+    fn payload() -> Vec<u8> {
+        let mut payload = vec![0; 256];
+        payload[0..4].copy_from_slice(b"UOSC");
+        payload[4..8].copy_from_slice(&USER_API_VERSION.to_le_bytes());
+        payload[8] = (K_USER_TARGET_NUTEKTDIGITAL >> 8) as u8;
+        for i in 0..7 {
+            let offset = HOOK_CALLBACKS_OFFSET + 4 * i;
+            payload[offset..offset + 4].copy_from_slice(&0x2000_0041u32.to_le_bytes());
+        }
+        payload
+    }
+
+    /// A unit file for raves in the temporary directory.
+    fn unit_file(test: &str, platform: &str) -> PathBuf {
+        let ext = unit::extension(platform).unwrap();
+        std::env::temp_dir().join(format!("xtask-inspect-{}-{}.{}", std::process::id(), test, ext))
+    }
+
+    fn inspect_unit(test: &str, platform: &str, payload: &[u8]) -> Vec<String> {
+        let path = unit_file(test, platform);
+        let json = manifest_json(manifest("raves").unwrap(), platform).unwrap();
+        write_unit(&path, "raves", json.as_bytes(), payload).unwrap();
+        let problems = inspect(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        problems
+    }
+
+    #[test]
+    fn sound_unit() {
+        assert_eq!(inspect_unit("sound", "nutekt-digital", &payload()), Vec::<String>::new());
+    }
+
+    #[test]
+    fn wrong_platform() {
+        assert_eq!(inspect_unit("platform", "prologue", &payload()), ["hook table and manifest platforms differ"]);
+    }
+
+    #[test]
+    fn wrong_magic() {
+        let mut payload = payload();
+        payload[0..4].copy_from_slice(b"UMOD");
+        assert_eq!(inspect_unit("magic", "nutekt-digital", &payload), ["hook table magic is not UOSC"]);
+    }
+
+    #[test]
+    fn even_callback() {
+        let mut payload = payload();
+        payload[HOOK_CALLBACKS_OFFSET + 4..HOOK_CALLBACKS_OFFSET + 8].copy_from_slice(&0x2000_0040u32.to_le_bytes());
+        payload[HOOK_CALLBACKS_OFFSET + 8..HOOK_CALLBACKS_OFFSET + 12].copy_from_slice(&0x2000_0101u32.to_le_bytes());
+        assert_eq!(inspect_unit("callback", "nutekt-digital", &payload),
+                   ["cycle callback is not Thumb code",
+                    "on callback is outside the payload (0x20000000..0x20000100)"]);
+    }
+
+    #[test]
+    fn extra_file() {
+        let path = unit_file("extra", "nutekt-digital");
+        let json = manifest_json(manifest("raves").unwrap(), "nutekt-digital").unwrap();
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        let options = FileOptions::default();
+        for (name, data) in [("raves/manifest.json", json.as_bytes()), ("raves/payload.bin", &payload()[..]),
+                             ("raves/README", &b"raves"[..])] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap();
+        let problems = inspect(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(problems, ["unexpected file in archive: raves/README"]);
+    }
+
+    #[test]
+    fn out_of_range_param() {
+        let path = unit_file("param", "nutekt-digital");
+        let json = manifest_json(manifest("raves").unwrap(), "nutekt-digital").unwrap()
+            .replace("[\"Sub Mix\",     0, 100,", "[\"Sub Mix\",     0, 4294967396,");
+        write_unit(&path, "raves", json.as_bytes(), &payload()).unwrap();
+        let problems = inspect(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(problems, ["malformed parameter descriptors", "num_param does not match the 5 parameters listed"]);
+    }
+}
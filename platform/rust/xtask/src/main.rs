@@ -0,0 +1,69 @@
+//! Host-side tasks for logue units written in Rust. Run from anywhere in
+//! the workspace with `cargo xtask <task>`.
+
+use std::env;
+use std::process;
+
+mod binary;
+mod budget;
+mod inspect;
+mod package;
+mod panics;
+mod size;
+#[cfg(test)]
+mod testelf;
+mod unit;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const USAGE: &str = "\
+usage: cargo xtask <task> [options]
+
+tasks:
+    package [--platform <name>] [--out <dir>] [<unit>]
+        Build <unit> (default: raves) for a platform (default:
+        nutekt-digital) and bundle it into a unit file in its directory,
+        or in <dir>. Fail if it does not fit in its SRAM region.
+    size [--platform <name>] [--top <n>] [<unit>]
+        Build <unit> as for package, but with no limit on its size, and
+        report the SRAM its sections take, against the space its kind
+        of unit has, and its <n> (default: 10) largest symbols. Fail if
+        it does not fit.
+    panics [--platform <name>] [<unit>]
+        Build <unit> as for package and list the branches and
+        references in it that can reach a panic, which halts the unit.
+        Fail if there are any.
+    budget [--platform <name>] [--clock <MHz>] [--max <percent>]
+           [--seeds <n>] [<unit>]
+        Build <unit> as for package and play <n> (default: 10) random
+        schedules of callbacks to its payload on an emulated Cortex-M4.
+        Report the estimated cycles each callback takes against a
+        64-frame block at <MHz> (default: 180), and fail if any but
+        init takes more than <percent> (default: 50) of it.
+    inspect <unit file>
+        Describe a unit file and check it for problems that would stop
+        it from loading.
+";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("package") => package::run(&args[1..]),
+        Some("budget") => budget::run(&args[1..]),
+        Some("size") => size::run(&args[1..]),
+        Some("panics") => panics::run(&args[1..]),
+        Some("inspect") => inspect::run(&args[1..]),
+        Some("help") | Some("--help") | Some("-h") => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        _ => {
+            eprint!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,137 @@
+//! `cargo xtask package`: build a unit and bundle it into the archive
+//! the Librarian and `logue-cli` load.
+//!
+//! A unit file is a zip archive with a single directory named after the
+//! unit, holding `manifest.json` and the raw `payload.bin`. The
+//! manifest is written for the platform from the one the unit declares
+//! in Rust (see `logue::manifest`).
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use logue::manifest::Manifest;
+use zip::write::{FileOptions, ZipWriter};
+
+use crate::binary::elf_to_binary;
+use crate::size;
+use crate::unit::{self, TARGET};
+use crate::Result;
+
+pub fn run(args: &[String]) -> Result<()> {
+    let mut platform = "nutekt-digital".to_string();
+    let mut name = "raves".to_string();
+    let mut out_dir = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--platform" => {
+                platform = args.next().ok_or("--platform needs a value")?.clone();
+            }
+            "--out" => {
+                out_dir = Some(PathBuf::from(args.next().ok_or("--out needs a value")?));
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg).into()),
+            _ => name = arg.clone(),
+        }
+    }
+    let ext = unit::extension(&platform)?;
+    let json = manifest_json(manifest(&name)?, &platform)?;
+    let (dir, payload) = build(&name, &platform)?;
+
+    let out = out_dir.unwrap_or(dir).join(format!("{}.{}", name, ext));
+    write_unit(&out, &name, json.as_bytes(), &payload)?;
+    println!("wrote {} ({} byte payload)", out.display(), payload.len());
+    Ok(())
+}
+
+/// The manifest a unit declares, checked against the firmware's limits.
+pub fn manifest(name: &str) -> Result<&'static Manifest> {
+    let manifest = logue_render::manifest(name).ok_or_else(|| format!("no manifest known for {}", name))?;
+    manifest.validate().map_err(|e| format!("invalid manifest for {}: {}", name, e))?;
+    Ok(manifest)
+}
+
+/// The `manifest.json` of a unit for a platform.
+pub fn manifest_json(manifest: &Manifest, platform: &str) -> Result<String> {
+    unit::extension(platform)?;
+    let mut json = String::new();
+    manifest.write_json(platform, &mut json)?;
+    Ok(json)
+}
+
+/// Build a unit for a platform, as it ships, returning its directory
+/// and its payload. Units that do not fit in their SRAM region fail to
+/// link, and are then linked again to report the space they take (see
+/// `size`).
+pub fn build(name: &str, platform: &str) -> Result<(PathBuf, Vec<u8>)> {
+    let (dir, elf) = match link(name, platform) {
+        Ok(linked) => linked,
+        Err(e) => {
+            if let Ok((_, elf)) = link_for_report(name, platform) {
+                size::Footprint::read(&elf)?.check(name)?;
+            }
+            return Err(e);
+        }
+    };
+    Ok((dir, elf_to_binary(&elf)?))
+}
+
+/// Build a unit for a platform, as it ships, returning its directory and
+/// the linked ELF file.
+pub fn link(name: &str, platform: &str) -> Result<(PathBuf, Vec<u8>)> {
+    cargo_link(name, platform, &[])
+}
+
+/// Build a unit for a platform as `link` does, but with the linker
+/// scripts' limit on SRAM lifted, so that units that do not fit link all
+/// the same, to be reported on. The result is not fit to ship.
+pub fn link_for_report(name: &str, platform: &str) -> Result<(PathBuf, Vec<u8>)> {
+    cargo_link(name, platform, &["-C", "link-arg=--defsym=_logue_size_report=1"])
+}
+
+fn cargo_link(name: &str, platform: &str, rustc_args: &[&str]) -> Result<(PathBuf, Vec<u8>)> {
+    let (dir, target_dir) = unit::locate(name)?;
+    let status = unit::cargo()
+        .args(["rustc", "--release", "--target", TARGET, "--package", name, "--bin", name,
+                "--no-default-features", "--features", platform, "--"])
+        .args(rustc_args)
+        .status()?;
+    if !status.success() {
+        return Err(format!("failed to build {}", name).into());
+    }
+
+    let elf = fs::read(target_dir.join(TARGET).join("release").join(name))?;
+    Ok((dir, elf))
+}
+
+/// Write the unit file archive.
+pub fn write_unit(path: &Path, name: &str, manifest: &[u8], payload: &[u8]) -> Result<()> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let options = FileOptions::default();
+    zip.add_directory(name, options)?;
+    zip.start_file(format!("{}/manifest.json", name), options)?;
+    zip.write_all(manifest)?;
+    zip.start_file(format!("{}/payload.bin", name), options)?;
+    zip.write_all(payload)?;
+    zip.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unit::PLATFORMS;
+
+    #[test]
+    fn manifest_for_platform() {
+        let raves = manifest("raves").unwrap();
+        for (platform, _) in PLATFORMS {
+            let json: serde_json::Value = serde_json::from_str(&manifest_json(raves, platform).unwrap()).unwrap();
+            assert_eq!(json["header"]["platform"], platform);
+            assert_eq!(json["header"]["name"], "raves");
+        }
+        assert!(manifest_json(raves, "monologue").is_err());
+        assert!(manifest("waves").is_err());
+    }
+}
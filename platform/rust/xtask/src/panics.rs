@@ -0,0 +1,349 @@
+//! `cargo xtask panics`: check that nothing in a unit can panic.
+//!
+//! Units halt on panic (see `panic-halt`), which on the synthesizers
+//! silently freezes the voice. Optimization removes the checks it can
+//! prove never fail, such as most slice bounds checks, but nothing in the
+//! source says which remain. This task builds the unit as for `package`
+//! and looks for what is left of the panic machinery in the linked ELF:
+//! the functions of `core::panicking`, the bounds check failures and the
+//! panic handler, `rust_begin_unwind`. It then follows the branches in
+//! the unit's code back from them, to find every function that can reach
+//! them, and lists the branches and address references into these
+//! functions from the rest of the unit. The task fails if there are any.
+//!
+//! Only direct branches are followed: a panic reached through a function
+//! pointer shows up as a reference to the function, where its address
+//! is taken.
+
+use object::read::elf::ElfFile32;
+use object::{Endianness, Object, ObjectSection, ObjectSymbol, SymbolKind};
+
+use crate::package::link;
+use crate::Result;
+
+pub fn run(args: &[String]) -> Result<()> {
+    let mut platform = "nutekt-digital".to_string();
+    let mut name = "raves".to_string();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--platform" => {
+                platform = args.next().ok_or("--platform needs a value")?.clone();
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg).into()),
+            _ => name = arg.clone(),
+        }
+    }
+
+    let (_, elf) = link(&name, &platform)?;
+    let sites = panic_sites(&elf)?;
+    if sites.is_empty() {
+        println!("no panicking paths in {}", name);
+        return Ok(());
+    }
+    for site in &sites {
+        println!("{:#010x} {}: {}", site.addr, site.place, site.path.join(" -> "));
+    }
+    Err(format!("{} site(s) in {} can reach a panic", sites.len(), name).into())
+}
+
+/// Whether a function is part of the panic machinery itself.
+fn is_panic(name: &str) -> bool {
+    name.starts_with("core::panicking::") || name.contains("panic_bounds_check") || name.ends_with("rust_begin_unwind")
+}
+
+/// A function in the unit's code.
+struct Function {
+    name: String,
+    start: u32,
+    end: u32,
+}
+
+/// A place in the unit that can lead to a panic.
+pub struct Site {
+    pub addr: u32,
+    /// The function and offset of the site, or the section of a
+    /// reference outside of functions.
+    pub place: String,
+    /// What the site leads to, from the function it branches to or takes
+    /// the address of, through the functions each calls, to the panic
+    /// machinery.
+    pub path: Vec<String>,
+}
+
+/// Find the sites of a linked unit that can lead to a panic.
+pub fn panic_sites(elf: &[u8]) -> Result<Vec<Site>> {
+    let file = ElfFile32::<Endianness>::parse(elf)?;
+
+    let mut functions = Vec::new();
+    // Mapping symbols, `$t` and `$d`, mark where code and data start in
+    // sections holding both, such as code followed by a literal pool.
+    let mut mapping = Vec::new();
+    for symbol in file.symbols() {
+        let name = symbol.name()?;
+        let addr = symbol.address() as u32;
+        if name == "$t" || name.starts_with("$t.") {
+            mapping.push((addr, true));
+        } else if name == "$d" || name.starts_with("$d.") {
+            mapping.push((addr, false));
+        } else if symbol.kind() == SymbolKind::Text && symbol.size() > 0 {
+            let start = addr & !1;
+            let name = format!("{:#}", rustc_demangle::demangle(name));
+            functions.push(Function { name, start, end: start + symbol.size() as u32 });
+        }
+    }
+    functions.sort_by(|a, b| a.start.cmp(&b.start).then_with(|| a.name.cmp(&b.name)));
+    functions.dedup_by_key(|f| f.start);
+    mapping.sort();
+    let is_code = |addr: u32| match mapping.partition_point(|&(a, _)| a <= addr) {
+        0 => true,
+        i => mapping[i - 1].1,
+    };
+    let containing = |addr: u32| functions.iter().position(|f| (f.start..f.end).contains(&addr));
+
+    // Direct branches between functions, as (site, caller, callee), and
+    // words holding the address of a function, as (site, callee).
+    let mut branches = Vec::new();
+    let mut references = Vec::new();
+    for section in file.sections() {
+        let base = section.address() as u32;
+        let data = match section.data() {
+            Ok(data) if section.address() != 0 => data,
+            _ => continue,
+        };
+        let halfword = |addr: u32| {
+            let i = (addr - base) as usize;
+            data.get(i..i + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+        };
+        let mut addr = base;
+        while let Some(hw1) = halfword(addr) {
+            match containing(addr).filter(|_| is_code(addr)) {
+                Some(caller) => {
+                    let (len, target) = decode(hw1, halfword(addr + 2).unwrap_or(0), addr);
+                    if let Some(callee) = target.and_then(containing) {
+                        if callee != caller {
+                            branches.push((addr, caller, callee));
+                        }
+                    }
+                    addr += len;
+                }
+                None => {
+                    if let (0, Some(hw2)) = (addr & 3, halfword(addr + 2)) {
+                        let word = hw1 as u32 | (hw2 as u32) << 16;
+                        if word & 1 == 1 {
+                            if let Some(callee) = functions.iter().position(|f| f.start == word & !1) {
+                                references.push((addr, callee));
+                            }
+                        }
+                    }
+                    addr += 2;
+                }
+            }
+        }
+    }
+
+    // The functions that can reach the panic machinery, each with the
+    // next function on its way there.
+    let mut next: Vec<Option<Option<usize>>> =
+        functions.iter().map(|f| if is_panic(&f.name) { Some(None) } else { None }).collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &(_, caller, callee) in &branches {
+            if next[caller].is_none() && next[callee].is_some() {
+                next[caller] = Some(Some(callee));
+                changed = true;
+            }
+        }
+    }
+    let path = |mut f: usize| {
+        let mut path = vec![functions[f].name.clone()];
+        while let Some(Some(g)) = next[f] {
+            path.push(functions[g].name.clone());
+            f = g;
+        }
+        path
+    };
+
+    let mut sites = Vec::new();
+    for &(addr, caller, callee) in &branches {
+        if next[callee].is_some() && !is_panic(&functions[caller].name) {
+            let f = &functions[caller];
+            sites.push(Site { addr, place: format!("{}+{:#x}", f.name, addr - f.start), path: path(callee) });
+        }
+    }
+    for &(addr, callee) in &references {
+        if next[callee].is_some() {
+            let place = match containing(addr) {
+                Some(f) => format!("{}+{:#x}", functions[f].name, addr - functions[f].start),
+                None => file.sections()
+                    .find(|s| (s.address()..s.address() + s.size()).contains(&(addr as u64)))
+                    .and_then(|s| s.name().ok().map(String::from))
+                    .unwrap_or_default(),
+            };
+            sites.push(Site { addr, place, path: path(callee) });
+        }
+    }
+    sites.sort_by_key(|s| s.addr);
+    Ok(sites)
+}
+
+/// Decode the Thumb instruction starting with `hw1`, followed by `hw2`,
+/// at `addr`, returning its length and, for a branch with an immediate
+/// offset (`B`, `B<c>` or `BL`), its target.
+fn decode(hw1: u16, hw2: u16, addr: u32) -> (u32, Option<u32>) {
+    let (hw1, hw2) = (hw1 as u32, hw2 as u32);
+    let sign_extend = |value: u32, bits: u32| ((value << (32 - bits)) as i32 >> (32 - bits)) as u32;
+    let pc = addr.wrapping_add(4);
+    if hw1 >> 11 < 0b11101 {
+        let target = if hw1 & 0xf800 == 0xe000 {
+            Some(sign_extend((hw1 & 0x7ff) << 1, 12))
+        } else if hw1 & 0xf000 == 0xd000 && (hw1 >> 8) & 0xf < 0xe {
+            Some(sign_extend((hw1 & 0xff) << 1, 9))
+        } else {
+            None
+        };
+        return (2, target.map(|offset| pc.wrapping_add(offset)));
+    }
+    if hw1 & 0xf800 != 0xf000 || hw2 & 0x8000 == 0 {
+        return (4, None);
+    }
+    let s = (hw1 >> 10) & 1;
+    let (j1, j2) = ((hw2 >> 13) & 1, (hw2 >> 11) & 1);
+    let imm11 = hw2 & 0x7ff;
+    let offset = match hw2 & 0xd000 {
+        // B.W and BL.
+        0x9000 | 0xd000 => {
+            let (i1, i2) = (!(j1 ^ s) & 1, !(j2 ^ s) & 1);
+            sign_extend(s << 24 | i1 << 23 | i2 << 22 | (hw1 & 0x3ff) << 12 | imm11 << 1, 25)
+        }
+        // B<c>.W, unless the condition field makes it another
+        // instruction.
+        0x8000 if (hw1 >> 6) & 0xf < 0xe => {
+            sign_extend(s << 20 | j2 << 19 | j1 << 18 | (hw1 & 0x3f) << 12 | imm11 << 1, 21)
+        }
+        _ => return (4, None),
+    };
+    (4, Some(pc.wrapping_add(offset)))
+}
+
+#[cfg(test)]
+mod tests {
+    use object::elf;
+
+    use super::*;
+    use crate::testelf::{self, Contents};
+
+    /// Instructions as `(first halfword, second halfword, address)`, with
+    /// the length and target `decode` should find, as assembled by
+    /// `llvm-mc` and listed by `llvm-objdump`.
+    const BRANCHES: [(u16, u16, u32, u32, Option<u32>); 14] = [
+        (0xe7fc, 0, 0x04, 2, Some(0x00)),             // b      back
+        (0xe017, 0, 0x06, 2, Some(0x38)),             // b      fwd
+        (0xd0fa, 0, 0x08, 2, Some(0x00)),             // beq    back
+        (0xd115, 0, 0x0a, 2, Some(0x38)),             // bne    fwd
+        (0xf010, 0xb815, 0x0c, 4, Some(0x1003a)),     // b.w    far
+        (0xf010, 0xf813, 0x10, 4, Some(0x1003a)),     // bl     far
+        (0xf7ff, 0xfff4, 0x14, 4, Some(0x00)),        // bl     back
+        (0xf000, 0x800e, 0x18, 4, Some(0x38)),        // beq.w  fwd
+        (0xf73f, 0xaff0, 0x1c, 4, Some(0x00)),        // bgt.w  back
+        (0xf6ff, 0xf7fd, 0x500002, 4, Some(0x00)),    // bl     farback
+        (0xf6ff, 0xb7fb, 0x500006, 4, Some(0x00)),    // b.w    farback
+        (0xf1ff, 0xf7fb, 0x50000a, 4, Some(0xb00004)), // bl     farfwd
+        (0xf6ff, 0x87fd, 0xc0002, 4, Some(0x00)),     // blt.w  a
+        (0xf240, 0xa800, 0xc0006, 4, Some(0x18000a)), // bls.w  b
+    ];
+
+    /// Instructions that are not branches with an immediate offset,
+    /// among them those a condition field of 0b111x makes something
+    /// else, with the same encodings.
+    const OTHERS: [(u16, u16, u32); 9] = [
+        (0xde00, 0, 2),      // udf    #0
+        (0xdf01, 0, 2),      // svc    #1
+        (0x6808, 0, 2),      // ldr    r0, [r1]
+        (0xb510, 0, 2),      // push   {r4, lr}
+        (0x4770, 0, 2),      // bx     lr
+        (0xf3af, 0x8000, 4), // nop.w
+        (0xf3ef, 0x8000, 4), // mrs    r0, apsr
+        (0xf380, 0x8800, 4), // msr    apsr_nzcvq, r0
+        (0xf04f, 0x0001, 4), // mov.w  r0, #1
+    ];
+
+    #[test]
+    fn decode_branches() {
+        for base in [0, 0x2000_0000] {
+            for (hw1, hw2, addr, len, target) in BRANCHES {
+                assert_eq!(decode(hw1, hw2, base + addr), (len, target.map(|t| base + t)),
+                           "{:04x} {:04x} at {:#x}", hw1, hw2, base + addr);
+            }
+        }
+    }
+
+    #[test]
+    fn decode_others() {
+        for (hw1, hw2, len) in OTHERS {
+            assert_eq!(decode(hw1, hw2, 0x2000_0000), (len, None), "{:04x} {:04x}", hw1, hw2);
+        }
+    }
+
+    const TEXT: u32 = 0x2000_0000;
+    const RODATA: u32 = 0x2000_0100;
+
+    /// A unit whose `index` checks its bounds, assembled from:
+    ///
+    /// ```text
+    /// cycle:  push  {r4, lr}          @ 0x00
+    ///         bl    index
+    ///         pop   {r4, pc}
+    /// index:  cmp   r0, #4            @ 0x08
+    ///         bhs   1f
+    ///         ldr.w r0, [r1, r0, lsl #2]
+    ///         bx    lr
+    /// 1:      bl    panic
+    /// pure:   ldr   r0, 2f            @ 0x16
+    ///         adds  r0, r0, #1
+    ///         bx    lr
+    /// 2:      .word index + 1         @ 0x1c
+    /// panic:  b     panic             @ 0x20
+    /// ```
+    ///
+    /// with a table of `cycle` and `pure` in `.rodata`.
+    fn unit() -> Vec<u8> {
+        let mut text = vec![
+            0x10, 0xb5, 0x00, 0xf0, 0x01, 0xf8, 0x10, 0xbd,
+            0x04, 0x28, 0x02, 0xd2, 0x51, 0xf8, 0x20, 0x00, 0x70, 0x47, 0x00, 0xf0, 0x05, 0xf8,
+            0x01, 0x48, 0x40, 0x1c, 0x70, 0x47,
+        ];
+        text.extend(((TEXT + 0x08) | 1).to_le_bytes());
+        text.extend([0xfe, 0xe7]);
+        let mut rodata = Vec::new();
+        rodata.extend((TEXT | 1).to_le_bytes());
+        rodata.extend(((TEXT + 0x16) | 1).to_le_bytes());
+        testelf::elf(&[
+            (".text", TEXT, Contents::Bytes(text)),
+            (".rodata", RODATA, Contents::Bytes(rodata)),
+        ], &[
+            ("$t", 0, TEXT, 0, elf::STT_NOTYPE),
+            ("$d", 0, TEXT + 0x1c, 0, elf::STT_NOTYPE),
+            ("$t", 0, TEXT + 0x20, 0, elf::STT_NOTYPE),
+            ("$d", 1, RODATA, 0, elf::STT_NOTYPE),
+            ("_ZN5raves5cycle17h0123456789abcdefE", 0, TEXT | 1, 0x08, elf::STT_FUNC),
+            ("_ZN5raves5index17h0123456789abcdefE", 0, (TEXT + 0x08) | 1, 0x0e, elf::STT_FUNC),
+            ("_ZN5raves4pure17h0123456789abcdefE", 0, (TEXT + 0x16) | 1, 0x0a, elf::STT_FUNC),
+            ("_ZN4core9panicking18panic_bounds_check17h0123456789abcdefE", 0, (TEXT + 0x20) | 1, 2, elf::STT_FUNC),
+        ])
+    }
+
+    #[test]
+    fn bounds_check_sites() {
+        let sites = panic_sites(&unit()).unwrap();
+        let sites: Vec<_> = sites.iter().map(|s| (s.addr, s.place.as_str(), s.path.join(" -> "))).collect();
+        let panic = "core::panicking::panic_bounds_check";
+        assert_eq!(sites, [
+            (TEXT + 0x02, "raves::cycle+0x2", format!("raves::index -> {}", panic)),
+            (TEXT + 0x12, "raves::index+0xa", panic.to_string()),
+            (TEXT + 0x1c, "raves::pure+0x6", format!("raves::index -> {}", panic)),
+            (RODATA, ".rodata", format!("raves::cycle -> raves::index -> {}", panic)),
+        ]);
+    }
+}
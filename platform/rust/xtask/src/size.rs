@@ -0,0 +1,216 @@
+//! `cargo xtask size`: report how much of its SRAM region a unit takes,
+//! and what takes it.
+//!
+//! The firmware loads a unit's code, constants and data into a fixed
+//! region of SRAM (32K for oscillators, less for effects), with its
+//! `.bss` after them. The linker scripts enforce the limit, but a unit
+//! over it only gets "section will not fit in region" from the linker.
+//! This task, and `package` once a unit fails to link, build the unit
+//! again with `_logue_size_report` defined, which lifts the limit in the
+//! scripts, so that the unit links and `Footprint` can say by how much it
+//! is over, and which symbols are the largest.
+
+use object::read::elf::ElfFile32;
+use object::{Endianness, Object, ObjectSection, ObjectSymbol, SectionFlags, SymbolKind};
+
+use crate::package::link_for_report;
+use crate::unit::{layout_by_magic, Layout};
+use crate::Result;
+
+/// The sections listed even when they are empty.
+const MAIN_SECTIONS: [&str; 5] = [".hooks", ".text", ".rodata", ".data", ".bss"];
+
+/// The size of the SRAM region when the limit is lifted, as in the
+/// linker scripts.
+const REPORT_LENGTH: u32 = 1024 * 1024;
+
+pub fn run(args: &[String]) -> Result<()> {
+    let mut platform = "nutekt-digital".to_string();
+    let mut name = "raves".to_string();
+    let mut top = 10;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |option: &str| args.next().ok_or(format!("{} needs a value", option));
+        match arg.as_str() {
+            "--platform" => platform = value(arg)?.clone(),
+            "--top" => top = value(arg)?.parse().map_err(|_| "--top needs a count")?,
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg).into()),
+            _ => name = arg.clone(),
+        }
+    }
+
+    let (_, elf) = link_for_report(&name, &platform)?;
+    let footprint = Footprint::read(&elf)?;
+    footprint.print(top);
+    footprint.check(&name)
+}
+
+/// What a linked unit puts in its SRAM region.
+pub struct Footprint {
+    layout: Layout,
+    /// The allocated sections in the region, as `(name, address, size)`,
+    /// in address order.
+    sections: Vec<(String, u32, u32)>,
+    /// The functions and objects in the region, as `(name, section,
+    /// size)`, largest first.
+    symbols: Vec<(String, String, u32)>,
+}
+
+impl Footprint {
+    /// Read the footprint of a linked unit, whose kind is given by the
+    /// magic of its hook table.
+    pub fn read(elf: &[u8]) -> Result<Footprint> {
+        let file = ElfFile32::<Endianness>::parse(elf)?;
+        let hooks = file.section_by_name(".hooks").ok_or("unit has no .hooks section")?;
+        let magic = hooks.data()?.get(..4).ok_or("unit has an empty .hooks section")?;
+        let layout = layout_by_magic(magic)
+            .ok_or_else(|| format!("unknown hook table magic {}", String::from_utf8_lossy(magic)))?;
+        let region = layout.origin as u64..(layout.origin + REPORT_LENGTH) as u64;
+
+        let mut sections = Vec::new();
+        for section in file.sections() {
+            let alloc = match section.flags() {
+                SectionFlags::Elf { sh_flags } => sh_flags & object::elf::SHF_ALLOC as u64 != 0,
+                _ => false,
+            };
+            if alloc && region.contains(&section.address()) {
+                sections.push((section.name()?.to_string(), section.address() as u32, section.size() as u32));
+            }
+        }
+        sections.sort_by_key(|s| s.1);
+
+        let mut symbols = Vec::new();
+        for symbol in file.symbols() {
+            if !matches!(symbol.kind(), SymbolKind::Text | SymbolKind::Data) || symbol.size() == 0
+                || !region.contains(&symbol.address()) {
+                continue;
+            }
+            let section = match symbol.section_index() {
+                Some(index) => file.section_by_index(index)?.name()?.to_string(),
+                None => continue,
+            };
+            let name = format!("{:#}", rustc_demangle::demangle(symbol.name()?));
+            symbols.push((name, section, symbol.size() as u32));
+        }
+        symbols.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+
+        Ok(Footprint { layout, sections, symbols })
+    }
+
+    /// The bytes of SRAM taken, from the origin of the region to the end
+    /// of the last section in it, `.bss` included.
+    pub fn used(&self) -> u32 {
+        self.sections.iter().map(|s| s.1 + s.2 - self.layout.origin).max().unwrap_or(0)
+    }
+
+    /// Print the sections in the region and the `top` largest symbols.
+    pub fn print(&self, top: usize) {
+        let used = self.used();
+        println!("{} of {} bytes of SRAM ({:.1}%) at {:#010x}",
+                 used, self.layout.length, 100.0 * used as f64 / self.layout.length as f64, self.layout.origin);
+        println!("{:<14} {:>10} {:>8}", "section", "address", "size");
+        for main in MAIN_SECTIONS {
+            if !self.sections.iter().any(|s| s.0 == main) {
+                println!("{:<14} {:>10} {:>8}", main, "-", 0);
+            }
+        }
+        for (name, addr, size) in &self.sections {
+            if *size > 0 || MAIN_SECTIONS.contains(&name.as_str()) {
+                println!("{:<14} {:#010x} {:>8}", name, addr, size);
+            }
+        }
+
+        println!("largest symbols:");
+        println!("{:>8} {:<10} name", "size", "section");
+        for (name, section, size) in self.symbols.iter().take(top) {
+            println!("{:>8} {:<10} {}", size, section, name);
+        }
+    }
+
+    /// Fail if the unit does not fit in its region.
+    pub fn check(&self, name: &str) -> Result<()> {
+        let used = self.used();
+        if used > self.layout.length {
+            return Err(format!("{} needs {} bytes of SRAM, {} more than the {} that {} may take \
+                                (see `cargo xtask size`)",
+                               name, used, used - self.layout.length, self.layout.length, self.layout.kind).into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use logue::userprg::K_USER_MODULE_OSC;
+    use object::elf;
+
+    use super::*;
+    use crate::testelf::{self, Contents};
+    use crate::unit::{layout, HOOK_TABLE_SIZE};
+
+    /// An oscillator with the given `.bss` size after 8K of code and
+    /// constants.
+    fn footprint(bss: u32) -> Footprint {
+        let layout = layout(K_USER_MODULE_OSC).unwrap();
+        let origin = layout.origin;
+        let sections = vec![
+            (".hooks".to_string(), origin, 0x40),
+            (".text".to_string(), origin + 0x40, 0x1bc0),
+            (".rodata".to_string(), origin + 0x1c00, 0x400),
+            (".bss".to_string(), origin + 0x2000, bss),
+        ];
+        Footprint { layout, sections, symbols: Vec::new() }
+    }
+
+    #[test]
+    fn used() {
+        assert_eq!(footprint(0).used(), 0x2000);
+        assert_eq!(footprint(0x100).used(), 0x2100);
+        let empty = Footprint { sections: Vec::new(), ..footprint(0) };
+        assert_eq!(empty.used(), 0);
+    }
+
+    #[test]
+    fn check() {
+        assert!(footprint(0).check("unit").is_ok());
+        assert!(footprint(0x6000).check("unit").is_ok());
+        let over = footprint(0x6000 + 100).check("unit").unwrap_err().to_string();
+        assert!(over.starts_with("unit needs 32868 bytes of SRAM, 100 more than the 32768 that oscillators may take"),
+                "{}", over);
+    }
+
+    /// A linked oscillator with its hook table, `text` bytes of code in
+    /// a function, and a `bss`-byte object in `.bss`.
+    fn oscillator_elf(text: u32, bss: u32) -> Vec<u8> {
+        let origin = layout(K_USER_MODULE_OSC).unwrap().origin;
+        let mut hooks = vec![0; HOOK_TABLE_SIZE];
+        hooks[..4].copy_from_slice(b"UOSC");
+        let text_addr = origin + HOOK_TABLE_SIZE as u32;
+        let bss_addr = text_addr + text;
+        testelf::elf(&[
+            (".hooks", origin, Contents::Bytes(hooks)),
+            (".text", text_addr, Contents::Bytes(vec![0; text as usize])),
+            (".bss", bss_addr, Contents::Zeroed(bss)),
+        ], &[
+            ("_hook_cycle", 1, text_addr | 1, text, elf::STT_FUNC),
+            ("_ZN5raves6BUFFER17h0123456789abcdefE", 2, bss_addr, bss, elf::STT_OBJECT),
+        ])
+    }
+
+    #[test]
+    fn read_over_limit() {
+        let fits = Footprint::read(&oscillator_elf(0x1000, 0x1000)).unwrap();
+        assert_eq!(fits.used(), 0x2040);
+        assert!(fits.check("unit").is_ok());
+
+        let over = Footprint::read(&oscillator_elf(0x1000, 0x8000)).unwrap();
+        let sections: Vec<_> = over.sections.iter().map(|s| s.0.as_str()).collect();
+        assert_eq!(sections, [".hooks", ".text", ".bss"]);
+        assert_eq!(over.symbols, [
+            ("raves::BUFFER".to_string(), ".bss".to_string(), 0x8000),
+            ("_hook_cycle".to_string(), ".text".to_string(), 0x1000),
+        ]);
+        assert_eq!(over.used(), 0x9040);
+        assert!(over.check("unit").unwrap_err().to_string().contains("4160 more than the 32768"));
+    }
+}
@@ -0,0 +1,86 @@
+//! Linked ELF files for tests, written from a list of sections and
+//! symbols, to stand in for units built for the device.
+
+use object::elf;
+use object::write::elf::{FileHeader, SectionHeader, Sym, Writer};
+use object::Endianness;
+
+/// A section, as `(name, address, contents)`, with no contents for
+/// `.bss`.
+pub type Section<'a> = (&'a str, u32, Contents);
+
+pub enum Contents {
+    Bytes(Vec<u8>),
+    /// Zeroed memory of the given size, not stored in the file.
+    Zeroed(u32),
+}
+
+/// A symbol, as `(name, index in the sections, value, size, type)`,
+/// where the type is one of the `elf::STT_*` constants. Mapping symbols,
+/// such as `$t`, are local; others are global.
+pub type Symbol<'a> = (&'a str, usize, u32, u32, u8);
+
+/// Write an executable for the Cortex-M4.
+pub fn elf(sections: &[Section], symbols: &[Symbol]) -> Vec<u8> {
+    let mut symbols = symbols.to_vec();
+    symbols.sort_by_key(|s| !s.0.starts_with('$'));
+    let locals = symbols.iter().filter(|s| s.0.starts_with('$')).count() as u32;
+
+    let mut out = Vec::new();
+    let mut w = Writer::new(Endianness::Little, false, &mut out);
+    w.reserve_file_header();
+    let names: Vec<_> = sections.iter().map(|s| w.add_section_name(s.0.as_bytes())).collect();
+    w.reserve_null_section_index();
+    let indices: Vec<_> = sections.iter().map(|_| w.reserve_section_index()).collect();
+    let offsets: Vec<_> = sections.iter().map(|s| match &s.2 {
+        Contents::Bytes(data) => w.reserve(data.len(), 4) as u64,
+        Contents::Zeroed(_) => 0,
+    }).collect();
+    let symbol_names: Vec<_> = symbols.iter().map(|s| w.add_string(s.0.as_bytes())).collect();
+    w.reserve_null_symbol_index();
+    for s in &symbols {
+        w.reserve_symbol_index(Some(indices[s.1]));
+    }
+    w.reserve_symtab_section_index();
+    w.reserve_symtab();
+    w.reserve_strtab_section_index();
+    w.reserve_strtab();
+    w.reserve_shstrtab_section_index();
+    w.reserve_shstrtab();
+    w.reserve_section_headers();
+
+    w.write_file_header(&FileHeader {
+        os_abi: 0, abi_version: 0, e_type: elf::ET_EXEC, e_machine: elf::EM_ARM, e_entry: 0, e_flags: 0,
+    }).unwrap();
+    for s in sections {
+        if let Contents::Bytes(data) = &s.2 {
+            w.write_align(4);
+            w.write(data);
+        }
+    }
+    w.write_null_symbol();
+    for (s, &name) in symbols.iter().zip(&symbol_names) {
+        let bind = if s.0.starts_with('$') { elf::STB_LOCAL } else { elf::STB_GLOBAL };
+        w.write_symbol(&Sym {
+            name: Some(name), section: Some(indices[s.1]), st_info: bind << 4 | s.4,
+            st_other: 0, st_shndx: 0, st_value: s.2 as u64, st_size: s.3 as u64,
+        });
+    }
+    w.write_strtab();
+    w.write_shstrtab();
+    w.write_null_section_header();
+    for ((s, &name), &sh_offset) in sections.iter().zip(&names).zip(&offsets) {
+        let (sh_type, sh_size) = match &s.2 {
+            Contents::Bytes(data) => (elf::SHT_PROGBITS, data.len() as u64),
+            Contents::Zeroed(size) => (elf::SHT_NOBITS, *size as u64),
+        };
+        w.write_section_header(&SectionHeader {
+            name: Some(name), sh_type, sh_flags: elf::SHF_ALLOC as u64, sh_addr: s.1 as u64, sh_offset, sh_size,
+            sh_link: 0, sh_info: 0, sh_addralign: 4, sh_entsize: 0,
+        });
+    }
+    w.write_symtab_section_header(1 + locals);
+    w.write_strtab_section_header();
+    w.write_shstrtab_section_header();
+    out
+}
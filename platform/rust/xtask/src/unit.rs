@@ -0,0 +1,99 @@
+//! Facts about unit files shared by the tasks.
+
+use std::path::PathBuf;
+use std::process::Command;
+
+use logue::userprg::*;
+
+use crate::Result;
+
+/// The target all units are built for.
+pub const TARGET: &str = "thumbv7em-none-eabihf";
+
+/// The platforms units can be built for, with the extension of their
+/// unit files.
+pub const PLATFORMS: [(&str, &str); 3] = [
+    ("prologue", "prlgunit"),
+    ("minilogue-xd", "mnlgxdunit"),
+    ("nutekt-digital", "ntkdigunit"),
+];
+
+/// Where a kind of unit is loaded, as in its linker script.
+pub struct Layout {
+    /// What the kind of unit is called, in the plural.
+    pub kind: &'static str,
+    pub magic: &'static [u8; 4],
+    pub origin: u32,
+    pub length: u32,
+    pub callbacks: &'static [&'static str],
+}
+
+/// The modules with a layout, in the order `layout` lists them.
+const MODULES: [u32; 4] = [K_USER_MODULE_OSC, K_USER_MODULE_MODFX, K_USER_MODULE_DELFX, K_USER_MODULE_REVFX];
+
+/// The layout of a kind of unit.
+pub fn layout(module: u32) -> Option<Layout> {
+    const FX_CALLBACKS: &[&str] = &["entry", "process", "suspend", "resume", "param"];
+    match module {
+        K_USER_MODULE_OSC => Some(Layout {
+            kind: "oscillators", magic: b"UOSC", origin: 0x2000_0000, length: 32 * 1024,
+            callbacks: &["entry", "cycle", "on", "off", "mute", "value", "param"],
+        }),
+        K_USER_MODULE_MODFX => Some(Layout {
+            kind: "modulation effects", magic: b"UMOD", origin: 0x2001_7800, length: 6 * 1024,
+            callbacks: FX_CALLBACKS,
+        }),
+        K_USER_MODULE_DELFX => Some(Layout {
+            kind: "delay effects", magic: b"UDEL", origin: 0x2001_9000, length: 12 * 1024,
+            callbacks: FX_CALLBACKS,
+        }),
+        K_USER_MODULE_REVFX => Some(Layout {
+            kind: "reverb effects", magic: b"UREV", origin: 0x2001_9000, length: 12 * 1024,
+            callbacks: FX_CALLBACKS,
+        }),
+        _ => None,
+    }
+}
+
+/// The layout of the kind of unit whose hook table starts with `magic`.
+pub fn layout_by_magic(magic: &[u8]) -> Option<Layout> {
+    MODULES.iter().filter_map(|&m| layout(m)).find(|l| l.magic == magic)
+}
+
+/// Size of every hook table, including its reserved callback slots.
+pub const HOOK_TABLE_SIZE: usize = 64;
+
+/// The unit file extension for a platform.
+pub fn extension(platform: &str) -> Result<&'static str> {
+    PLATFORMS.iter()
+        .find(|(p, _)| *p == platform)
+        .map(|(_, ext)| *ext)
+        .ok_or_else(|| format!("unknown platform: {}", platform).into())
+}
+
+/// The `cargo` to run for nested builds.
+pub fn cargo() -> Command {
+    Command::new(std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into()))
+}
+
+/// Look up a workspace package with `cargo metadata`, returning its
+/// directory and the workspace's target directory.
+pub fn locate(package: &str) -> Result<(PathBuf, PathBuf)> {
+    let out = cargo()
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .output()?;
+    if !out.status.success() {
+        return Err(String::from_utf8_lossy(&out.stderr).into_owned().into());
+    }
+    let meta: serde_json::Value = serde_json::from_slice(&out.stdout)?;
+    let target_dir = meta["target_directory"].as_str()
+        .ok_or("cargo metadata has no target directory")?;
+    let manifest = meta["packages"].as_array()
+        .into_iter()
+        .flatten()
+        .find(|p| p["name"] == package)
+        .and_then(|p| p["manifest_path"].as_str())
+        .ok_or_else(|| format!("no package named {} in the workspace", package))?;
+    let dir = PathBuf::from(manifest).parent().unwrap().to_path_buf();
+    Ok((dir, PathBuf::from(target_dir)))
+}